@@ -1,6 +1,6 @@
 use anyhow::{anyhow, Context, Result};
 use semver::Version;
-use shell_candy::{ShellTask, ShellTaskBehavior, ShellTaskLog, ShellTaskOutput};
+use shell_candy::{ShellTask, ShellTaskBehavior, ShellTaskOutput};
 
 fn main() -> Result<()> {
     // Create a task to check the current version of `rustc` that is installed
@@ -10,7 +10,7 @@ fn main() -> Result<()> {
     eprintln!("{}", task.bash_descriptor());
 
     // Run the command with a handler closure that swallows all output
-    let task_result = task.run(|line| ShellTaskBehavior::<()>::Passthrough)?;
+    let task_result = task.run(|_line| ShellTaskBehavior::<()>::Passthrough)?;
 
     let rustc_version = match task_result {
         ShellTaskOutput::CompleteOutput { stdout_lines, .. }
@@ -1,15 +1,20 @@
+use crate::ShellTaskStream;
 #[cfg(doc)]
-use crate::ShellTask;
+use crate::{ShellPipeline, ShellTask};
 
 use thiserror::Error as ThisError;
 
-use std::{io, process::ExitStatus};
+use std::{fmt, io, path::PathBuf, process::ExitStatus, time::Duration};
 
 /// The result type used by a [`ShellTask`].
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// The possible errors reported by a [`ShellTask`].
+///
+/// Some variants only exist when an optional feature such as `semver` or `regex` is enabled,
+/// so matches on [`Error`] must include a wildcard arm.
 #[derive(ThisError, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// This error occurs when a command exits with a status other than 0.
     #[error("'{task}' failed with {exit_status}.")]
@@ -19,6 +24,134 @@ pub enum Error {
 
         /// The exit status that was returned.
         exit_status: ExitStatus,
+
+        /// The lines printed to `stdout` by the task, limited by [`ShellTask::failure_output_tail`].
+        stdout_lines: Vec<String>,
+
+        /// The lines printed to `stderr` by the task, limited by [`ShellTask::failure_output_tail`].
+        stderr_lines: Vec<String>,
+    },
+
+    /// This error occurs when the shell running a task created with [`ShellTask::shell`] fails on its own,
+    /// rather than passing along the exit status of the script, such as when a command in the script does not exist.
+    #[error("'{task}' could not be run by '{shell}' because {reason} ({exit_status}).")]
+    ShellFailure {
+        /// The script that failed.
+        task: String,
+
+        /// The shell command that ran the script.
+        shell: String,
+
+        /// The reason the shell failed.
+        reason: String,
+
+        /// The exit status that was returned.
+        exit_status: ExitStatus,
+
+        /// The lines printed to `stdout` by the task, limited by [`ShellTask::failure_output_tail`].
+        stdout_lines: Vec<String>,
+
+        /// The lines printed to `stderr` by the task, limited by [`ShellTask::failure_output_tail`].
+        stderr_lines: Vec<String>,
+    },
+
+    /// This error occurs when a stage of a [`ShellPipeline`] fails. If several stages fail,
+    /// this describes the rightmost one, like `set -o pipefail`. Stages are numbered from 0,
+    /// like [`crate::ShellPipelineLog::stage`].
+    #[error("'{pipeline}' failed at stage {stage}: {source}")]
+    PipelineFailure {
+        /// The pipeline that failed.
+        pipeline: String,
+
+        /// The index of the stage that failed, starting at 0.
+        stage: usize,
+
+        /// The error describing how the stage failed.
+        source: Box<Error>,
+    },
+
+    /// This error occurs when a task run with [`crate::ShellTask::retry`] fails on its last attempt,
+    /// either because it ran out of attempts or because [`crate::RetryPolicy::retry_if`] declined to retry.
+    #[error("'{task}' failed after {attempts} {}: {source}", if *attempts == 1 { "attempt" } else { "attempts" })]
+    RetryFailure {
+        /// The task that failed.
+        task: String,
+
+        /// The number of times the task ran.
+        attempts: usize,
+
+        /// The error describing how the last attempt failed.
+        source: Box<Error>,
+    },
+
+    /// This error occurs when a command is terminated by a signal instead of exiting. Only reported on Unix.
+    #[error("'{task}' was terminated by {}{}.", describe_signal(*signal), if *core_dumped { " (core dumped)" } else { "" })]
+    TaskKilledBySignal {
+        /// The task that was terminated.
+        task: String,
+
+        /// The number of the signal that terminated the task.
+        signal: i32,
+
+        /// Whether the task produced a core dump.
+        core_dumped: bool,
+
+        /// The lines printed to `stdout` by the task, limited by [`ShellTask::failure_output_tail`].
+        stdout_lines: Vec<String>,
+
+        /// The lines printed to `stderr` by the task, limited by [`ShellTask::failure_output_tail`].
+        stderr_lines: Vec<String>,
+    },
+
+    /// This error occurs when a command is still running after its [`ShellTask::timeout`] and is killed.
+    #[error("'{task}' timed out after {timeout:?}.")]
+    TimedOut {
+        /// The task that timed out.
+        task: String,
+
+        /// The timeout that was exceeded.
+        timeout: Duration,
+
+        /// The lines printed to `stdout` by the task, limited by [`ShellTask::failure_output_tail`].
+        stdout_lines: Vec<String>,
+
+        /// The lines printed to `stderr` by the task, limited by [`ShellTask::failure_output_tail`].
+        stderr_lines: Vec<String>,
+    },
+
+    /// This error occurs when a service started with [`ShellTask::start_service`] exits before it is ready.
+    #[error("'{task}' exited with {exit_status} before it was ready.")]
+    ServiceExited {
+        /// The service that exited.
+        task: String,
+
+        /// The exit status of the service.
+        exit_status: ExitStatus,
+
+        /// The lines printed to `stdout` by the service, limited by [`ShellTask::failure_output_tail`].
+        stdout_lines: Vec<String>,
+
+        /// The lines printed to `stderr` by the service, limited by [`ShellTask::failure_output_tail`].
+        stderr_lines: Vec<String>,
+    },
+
+    /// This error occurs when a task in a [`crate::ShellTaskGroup`] is killed, or never started,
+    /// because another task in the group failed.
+    #[error("'{task}' was cancelled because another task failed.")]
+    Cancelled {
+        /// The task that was cancelled.
+        task: String,
+    },
+
+    /// This error occurs when the tasks of a [`crate::ShellTaskGraph`] depend on each other in a cycle.
+    #[error(
+        "the task graph contains a dependency cycle: {}.",
+        describe_cycle(cycle)
+    )]
+    DependencyCycle {
+        /// The descriptors of the tasks in the cycle, where each task runs after the one before it
+        /// and the first task runs after the last.
+        cycle: Vec<String>,
     },
 
     /// This error occurs when a task could not be instantiated because it was malformed.
@@ -32,6 +165,69 @@ pub enum Error {
         reason: String,
     },
 
+    /// This error occurs when the binary for a task could not be found in its `PATH` or directory.
+    #[error("{}", describe_missing_binary(task, bin, searched_paths, suggestions, install_hint.as_deref()))]
+    BinaryNotFound {
+        /// The task whose binary could not be found.
+        task: String,
+
+        /// The binary that could not be found.
+        bin: String,
+
+        /// The directories that were searched for the binary.
+        searched_paths: Vec<PathBuf>,
+
+        /// Executables with names similar to `bin` that were found while searching, closest first.
+        suggestions: Vec<String>,
+
+        /// The hint registered for `bin` with [`ShellTask::register_install_hint`], if any.
+        install_hint: Option<String>,
+    },
+
+    /// This error occurs when the version requirement passed to [`ShellTask::require_version`] is malformed.
+    #[cfg(feature = "semver")]
+    #[error("'{requirement}' is not a valid version requirement: {source}.")]
+    InvalidVersionRequirement {
+        /// The malformed version requirement.
+        requirement: String,
+
+        /// The [`semver::Error`] that was reported by [`semver::VersionReq::parse`].
+        source: semver::Error,
+    },
+
+    /// This error occurs when [`ShellTask::require_version`] could not find a version in the output of a task.
+    #[cfg(feature = "semver")]
+    #[error("could not find a version in the output of '{task}'.")]
+    VersionNotFound {
+        /// The task that was run to find the version.
+        task: String,
+    },
+
+    /// This error occurs when the version found by [`ShellTask::require_version`] does not meet the requirement.
+    #[cfg(feature = "semver")]
+    #[error("'{task}' reported version {found}, but {required} is required.")]
+    UnsupportedVersion {
+        /// The task that was run to find the version.
+        task: String,
+
+        /// The version that was found.
+        found: semver::Version,
+
+        /// The version requirement that was not met.
+        required: semver::VersionReq,
+    },
+
+    /// This error occurs when a pattern passed to a [`crate::ShellTaskMatcher`] is not a valid regular expression.
+    #[cfg(feature = "regex")]
+    #[error("'{pattern}' is not a valid regular expression: {source}")]
+    InvalidPattern {
+        /// The malformed pattern.
+        pattern: String,
+
+        /// The [`regex::Error`] that was reported by [`regex::Regex::new`].
+        source: regex::Error,
+    },
+
     /// This error occurs when a task could not spawn. Originates from [`std::process::Command::spawn`].
     #[error("could not spawn '{task}': {source}.")]
     CouldNotSpawn {
@@ -52,6 +248,53 @@ pub enum Error {
         source: io::Error,
     },
 
+    /// There was an error reading a line of output from the task. Originates from [`std::io::BufRead::read_until`].
+    /// Output that is not valid UTF-8 is decoded lossily instead of causing this error.
+    #[error("could not read {stream} of '{task}': {source}.")]
+    CouldNotReadOutput {
+        /// The task whose output could not be read.
+        task: String,
+
+        /// The stream that could not be read.
+        stream: ShellTaskStream,
+
+        /// The [`io::Error`] that was reported by [`std::io::BufRead::read_until`].
+        source: io::Error,
+    },
+
+    /// This error is returned when a dotenv file could not be read. Originates from [`std::fs::read_to_string`].
+    #[error("could not read env file '{}': {source}.", path.display())]
+    CouldNotReadEnvFile {
+        /// The path to the dotenv file.
+        path: PathBuf,
+
+        /// The [`io::Error`] that was reported by [`std::fs::read_to_string`].
+        source: io::Error,
+    },
+
+    /// This error is returned when a dotenv file passed to [`ShellTask::env_file`] is malformed.
+    #[error("'{}' is not a valid env file because line {line} is malformed: {reason}.", path.display())]
+    InvalidEnvFile {
+        /// The path to the dotenv file.
+        path: PathBuf,
+
+        /// The 1-based number of the malformed line.
+        line: usize,
+
+        /// The reason the line is malformed.
+        reason: String,
+    },
+
+    /// This error is returned when the temporary file for [`ShellTask::script`] could not be written.
+    #[error("could not write script to '{}': {source}.", path.display())]
+    CouldNotWriteScript {
+        /// The path to the temporary script file.
+        path: PathBuf,
+
+        /// The [`io::Error`] that was reported while writing the script.
+        source: io::Error,
+    },
+
     /// This error is returned when the current directory cannot be found. Originates from [`std::env::current_dir`].
     #[error("could not find current directory when initializing task: {source}.")]
     CouldNotFindCurrentDirectory {
@@ -63,10 +306,164 @@ pub enum Error {
     #[error(transparent)]
     EarlyReturn(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
 
-    /// This error is returned when the log lock is poisoned.
-    #[error("encountered an unrecoverable error while processing logs for '{task}'.")]
-    PoisonedLog {
-        /// The task that encountered an unrecoverable error
+    /// This error is returned by [`crate::ShellTaskGroup`] and [`crate::ShellTaskGraph`] for a task whose run panicked
+    /// outside of its log handler, such as in a [`ShellTask::success_when`] predicate. The task is killed,
+    /// and the other tasks keep running.
+    #[error("running '{task}' panicked: {message}.")]
+    TaskPanicked {
+        /// The task whose run panicked.
         task: String,
+
+        /// The message the run panicked with.
+        message: String,
     },
+
+    /// This error is returned when a log handler panics. The task is killed before this error is returned.
+    #[error("the log handler for '{task}' panicked: {message}.")]
+    HandlerPanicked {
+        /// The task whose log handler panicked.
+        task: String,
+
+        /// The message the log handler panicked with.
+        message: String,
+    },
+}
+
+impl Error {
+    /// Returns the exit code of the task if it exited with a status other than 0.
+    /// Returns [`None`] for every other error, including [`Error::TaskKilledBySignal`].
+    pub fn exit_code(&self) -> Option<i32> {
+        match self {
+            Error::TaskFailure { exit_status, .. } | Error::ShellFailure { exit_status, .. } => {
+                exit_status.code()
+            }
+            Error::PipelineFailure { source, .. } | Error::RetryFailure { source, .. } => {
+                source.exit_code()
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the signal that terminated the task if this is an [`Error::TaskKilledBySignal`].
+    pub fn signal(&self) -> Option<i32> {
+        match self {
+            Error::TaskKilledBySignal { signal, .. } => Some(*signal),
+            Error::PipelineFailure { source, .. } | Error::RetryFailure { source, .. } => {
+                source.signal()
+            }
+            _ => None,
+        }
+    }
+
+    /// Displays this error followed by the last `lines` lines printed to `stderr`
+    /// when it is an [`Error::TaskFailure`], an [`Error::ShellFailure`], an [`Error::TaskKilledBySignal`],
+    /// an [`Error::TimedOut`], an [`Error::ServiceExited`], an [`Error::PipelineFailure`], or an [`Error::RetryFailure`].
+    /// Other errors display as usual.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use shell_candy::{ShellTask, ShellTaskBehavior};
+    ///
+    /// let error = ShellTask::new("rustc --not-a-real-flag")
+    ///     .unwrap()
+    ///     .run(|_| ShellTaskBehavior::<()>::Passthrough)
+    ///     .unwrap_err();
+    /// let message = error.display_with_stderr(5).to_string();
+    /// assert!(message.contains("Unrecognized option"));
+    /// ```
+    pub fn display_with_stderr(&self, lines: usize) -> impl fmt::Display + '_ {
+        DisplayWithStderr { error: self, lines }
+    }
+
+    /// Returns the lines printed to `stderr` by the task this error describes, if it collected them.
+    fn stderr_lines(&self) -> Option<&[String]> {
+        match self {
+            Error::TaskFailure { stderr_lines, .. }
+            | Error::ShellFailure { stderr_lines, .. }
+            | Error::TaskKilledBySignal { stderr_lines, .. }
+            | Error::TimedOut { stderr_lines, .. }
+            | Error::ServiceExited { stderr_lines, .. } => Some(stderr_lines),
+            Error::PipelineFailure { source, .. } | Error::RetryFailure { source, .. } => {
+                source.stderr_lines()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The [`fmt::Display`] adapter returned by [`Error::display_with_stderr`].
+struct DisplayWithStderr<'a> {
+    error: &'a Error,
+    lines: usize,
+}
+
+impl fmt::Display for DisplayWithStderr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        if let Some(stderr_lines) = self.error.stderr_lines() {
+            let skip = stderr_lines.len().saturating_sub(self.lines);
+            for line in &stderr_lines[skip..] {
+                write!(f, "\n    {}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Formats a dependency cycle as `a -> b -> a`.
+fn describe_cycle(cycle: &[String]) -> String {
+    let mut tasks: Vec<String> = cycle.iter().map(|task| format!("'{}'", task)).collect();
+    if let Some(first) = tasks.first().cloned() {
+        tasks.push(first);
+    }
+    tasks.join(" -> ")
+}
+
+/// Describes a signal number, including its name for the signals tasks are most commonly terminated by.
+fn describe_signal(signal: i32) -> String {
+    let name = match signal {
+        1 => "SIGHUP",
+        2 => "SIGINT",
+        3 => "SIGQUIT",
+        4 => "SIGILL",
+        6 => "SIGABRT",
+        8 => "SIGFPE",
+        9 => "SIGKILL",
+        11 => "SIGSEGV",
+        13 => "SIGPIPE",
+        15 => "SIGTERM",
+        _ => return format!("signal {}", signal),
+    };
+    format!("signal {} ({})", signal, name)
+}
+
+/// Describes a binary that could not be found, along with any suggestions and install hints.
+fn describe_missing_binary(
+    task: &str,
+    bin: &str,
+    searched_paths: &[PathBuf],
+    suggestions: &[String],
+    install_hint: Option<&str>,
+) -> String {
+    let mut description = format!(
+        "could not run '{}' because '{}' is not installed on this machine",
+        task, bin
+    );
+    if !searched_paths.is_empty() {
+        let searched_paths: Vec<_> = searched_paths
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect();
+        description.push_str(&format!(" (searched {})", searched_paths.join(", ")));
+    }
+    description.push('.');
+    if !suggestions.is_empty() {
+        let suggestions: Vec<_> = suggestions.iter().map(|s| format!("'{}'", s)).collect();
+        description.push_str(&format!(" did you mean {}?", suggestions.join(" or ")));
+    }
+    if let Some(install_hint) = install_hint {
+        description.push_str(&format!(" {}.", install_hint.trim_end_matches('.')));
+    }
+    description
 }
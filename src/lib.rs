@@ -1,5 +1,7 @@
 #![doc = include_str!("../README.md")]
-#![forbid(unsafe_code)]
+// `process::set_parent_death_signal` needs `pre_exec` on Linux; everywhere else there is no unsafe code at all
+#![cfg_attr(target_os = "linux", deny(unsafe_code))]
+#![cfg_attr(not(target_os = "linux"), forbid(unsafe_code))]
 #![deny(missing_docs, missing_debug_implementations, nonstandard_style)]
 
 mod error;
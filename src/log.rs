@@ -1,5 +1,9 @@
 #[cfg(doc)]
-use crate::ShellTask;
+use crate::{ShellPipeline, ShellTask, ShellTaskGraph, ShellTaskGroup, ShellTaskSequence};
+
+use std::fmt;
+
+use crate::ShellTaskId;
 
 /// A log message emitted by a [`ShellTask`].
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -10,3 +14,105 @@ pub enum ShellTaskLog {
     /// A log message emitted to `stderr`
     Stderr(String),
 }
+
+/// A log message emitted by one stage of a [`ShellPipeline`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ShellPipelineLog {
+    /// The index of the stage that emitted the log message, starting at 0.
+    pub stage: usize,
+
+    /// The log message.
+    pub log: ShellTaskLog,
+}
+
+/// A log message emitted by one task of a [`ShellTaskGroup`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ShellTaskGroupLog {
+    /// The index of the task that emitted the log message, in the order tasks were added, starting at 0.
+    pub task: usize,
+
+    /// The log message.
+    pub log: ShellTaskLog,
+}
+
+/// A log message emitted by one task of a [`ShellTaskGraph`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ShellTaskGraphLog {
+    /// The task that emitted the log message.
+    pub task: ShellTaskId,
+
+    /// The log message.
+    pub log: ShellTaskLog,
+}
+
+/// An event emitted while running a [`ShellTaskSequence`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShellTaskSequenceEvent {
+    /// A step is about to run.
+    StepStarted {
+        /// The index of the step, starting at 0.
+        step: usize,
+
+        /// The [`ShellTask::descriptor`] of the step.
+        descriptor: String,
+    },
+
+    /// A step emitted a log message.
+    Log {
+        /// The index of the step, starting at 0.
+        step: usize,
+
+        /// The log message.
+        log: ShellTaskLog,
+    },
+
+    /// A step has finished running.
+    StepFinished {
+        /// The index of the step, starting at 0.
+        step: usize,
+
+        /// The [`ShellTask::descriptor`] of the step.
+        descriptor: String,
+
+        /// Whether the step succeeded.
+        success: bool,
+    },
+}
+
+/// An event emitted while running a [`ShellTask`] with [`ShellTask::retry`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShellTaskRetryEvent {
+    /// An attempt is about to start.
+    AttemptStarted {
+        /// The number of the attempt, starting at 1.
+        attempt: usize,
+    },
+
+    /// The current attempt emitted a log message.
+    Log {
+        /// The number of the attempt, starting at 1.
+        attempt: usize,
+
+        /// The log message.
+        log: ShellTaskLog,
+    },
+}
+
+/// An output stream of a [`ShellTask`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ShellTaskStream {
+    /// The `stdout` stream
+    Stdout,
+
+    /// The `stderr` stream
+    Stderr,
+}
+
+impl fmt::Display for ShellTaskStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShellTaskStream::Stdout => write!(f, "stdout"),
+            ShellTaskStream::Stderr => write!(f, "stderr"),
+        }
+    }
+}
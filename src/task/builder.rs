@@ -0,0 +1,296 @@
+use std::{ffi::OsStr, path::Path, process::ExitStatus, time::Duration};
+
+use crate::{Result, ShellTask, ShellTaskCollection, ShellTaskStdin};
+
+/// [`ShellTaskBuilder`] configures a [`ShellTask`] with chained, by-value methods
+/// and checks the whole configuration at once in [`ShellTaskBuilder::build`].
+///
+/// Every method mirrors the [`ShellTask`] method of the same name. The first error
+/// encountered while configuring the task is reported by [`ShellTaskBuilder::build`].
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use shell_candy::{ShellTaskBehavior, ShellTaskBuilder, ShellTaskCollection, ShellTaskStdin};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+///     let task = ShellTaskBuilder::new("cargo")
+///         .arg("--version")
+///         .env("CARGO_TERM_COLOR", "never")
+///         .current_dir(".")
+///         .stdin(ShellTaskStdin::Null)
+///         .timeout(Duration::from_secs(60))
+///         .collect(ShellTaskCollection::Tail(10))
+///         .build()?;
+///     task.run(|_| ShellTaskBehavior::<()>::Passthrough)?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ShellTaskBuilder {
+    task: Result<ShellTask>,
+}
+
+impl ShellTaskBuilder {
+    /// Starts building a [`ShellTask`] like [`ShellTask::new`].
+    pub fn new(command: &str) -> Self {
+        Self {
+            task: ShellTask::new(command),
+        }
+    }
+
+    /// Starts building a [`ShellTask`] like [`ShellTask::shell`].
+    pub fn shell(script: &str) -> Self {
+        Self {
+            task: ShellTask::shell(script),
+        }
+    }
+
+    /// Checks the configuration and returns the [`ShellTask`]. This fails if any method reported an error,
+    /// the directory does not exist, the binary cannot be found, or the command cannot be expanded.
+    pub fn build(self) -> Result<ShellTask> {
+        let task = self.task?;
+        task.validate()?;
+        Ok(task)
+    }
+
+    /// Applies `configure` to the task if no errors have been encountered yet.
+    fn configure<F>(mut self, configure: F) -> Self
+    where
+        F: FnOnce(&mut ShellTask) -> Result<()>,
+    {
+        if let Ok(task) = &mut self.task {
+            if let Err(e) = configure(task) {
+                self.task = Err(e);
+            }
+        }
+        self
+    }
+
+    /// See [`ShellTask::arg`].
+    pub fn arg<A>(self, arg: A) -> Self
+    where
+        A: Into<String>,
+    {
+        self.configure(|task| {
+            task.arg(arg);
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::args`].
+    pub fn args<I, A>(self, args: I) -> Self
+    where
+        I: IntoIterator<Item = A>,
+        A: Into<String>,
+    {
+        self.configure(|task| {
+            task.args(args);
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::secret_arg`].
+    pub fn secret_arg<A>(self, arg: A) -> Self
+    where
+        A: Into<String>,
+    {
+        self.configure(|task| {
+            task.secret_arg(arg);
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::env`].
+    pub fn env<K, V>(self, key: K, value: V) -> Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.configure(|task| {
+            task.env(key, value);
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::envs`].
+    pub fn envs<I, K, V>(self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.configure(|task| {
+            task.envs(vars);
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::secret_env`].
+    pub fn secret_env<K, V>(self, key: K, value: V) -> Self
+    where
+        K: AsRef<OsStr>,
+        V: Into<String>,
+    {
+        self.configure(|task| {
+            task.secret_env(key, value);
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::env_remove`].
+    pub fn env_remove<K>(self, key: K) -> Self
+    where
+        K: AsRef<OsStr>,
+    {
+        self.configure(|task| {
+            task.env_remove(key);
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::env_clear`].
+    pub fn env_clear(self) -> Self {
+        self.configure(|task| {
+            task.env_clear();
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::env_inherit_only`].
+    pub fn env_inherit_only<I, K>(self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<OsStr>,
+    {
+        self.configure(|task| {
+            task.env_inherit_only(keys);
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::env_file`].
+    pub fn env_file<P>(self, path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.configure(|task| task.env_file(path).map(|_| ()))
+    }
+
+    /// See [`ShellTask::expand`].
+    pub fn expand(self) -> Self {
+        self.configure(|task| {
+            task.expand();
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::current_dir`].
+    pub fn current_dir<P>(self, path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.configure(|task| {
+            task.current_dir(path);
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::stdin`].
+    pub fn stdin<S>(self, stdin: S) -> Self
+    where
+        S: Into<ShellTaskStdin>,
+    {
+        self.configure(|task| {
+            task.stdin(stdin);
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::timeout`].
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.configure(|task| {
+            task.timeout(timeout);
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::process_group`].
+    pub fn process_group(self, enabled: bool) -> Self {
+        self.configure(|task| {
+            task.process_group(enabled);
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::die_with_parent`].
+    pub fn die_with_parent(self, enabled: bool) -> Self {
+        self.configure(|task| {
+            task.die_with_parent(enabled);
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::collect`].
+    pub fn collect(self, collection: ShellTaskCollection) -> Self {
+        self.configure(|task| {
+            task.collect(collection);
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::redact`].
+    pub fn redact<S>(self, secret: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.configure(|task| {
+            task.redact(secret);
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::failure_output_tail`].
+    pub fn failure_output_tail(self, lines: usize) -> Self {
+        self.configure(|task| {
+            task.failure_output_tail(lines);
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::success_codes`].
+    pub fn success_codes<I>(self, codes: I) -> Self
+    where
+        I: IntoIterator<Item = i32>,
+    {
+        self.configure(|task| {
+            task.success_codes(codes);
+            Ok(())
+        })
+    }
+
+    /// See [`ShellTask::success_when`].
+    pub fn success_when<F>(self, predicate: F) -> Self
+    where
+        F: Fn(&ExitStatus, &[String], &[String]) -> bool + Send + Sync + 'static,
+    {
+        self.configure(|task| {
+            task.success_when(predicate);
+            Ok(())
+        })
+    }
+}
+
+impl From<ShellTask> for ShellTaskBuilder {
+    fn from(task: ShellTask) -> Self {
+        Self { task: Ok(task) }
+    }
+}
+
+impl ShellTask {
+    /// Starts building a [`ShellTask`] with a [`ShellTaskBuilder`], like [`ShellTaskBuilder::new`].
+    pub fn builder(command: &str) -> ShellTaskBuilder {
+        ShellTaskBuilder::new(command)
+    }
+}
@@ -0,0 +1,52 @@
+use std::collections::VecDeque;
+
+#[cfg(doc)]
+use crate::{ShellTask, ShellTaskOutput};
+
+/// [`ShellTaskCollection`] decides which lines a [`ShellTask`] keeps in its [`ShellTaskOutput`].
+/// Every line is still passed to the log handler.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ShellTaskCollection {
+    /// Every line is kept.
+    #[default]
+    All,
+
+    /// Only the last `n` lines of each stream are kept, for long-running or chatty tasks.
+    Tail(usize),
+
+    /// No lines are kept.
+    Nothing,
+}
+
+/// The lines collected from one stream of a task, according to its [`ShellTaskCollection`].
+#[derive(Debug)]
+pub(crate) struct CollectedLines {
+    collection: ShellTaskCollection,
+    lines: VecDeque<String>,
+}
+
+impl CollectedLines {
+    pub(crate) fn new(collection: ShellTaskCollection) -> Self {
+        Self {
+            collection,
+            lines: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, line: &str) {
+        match self.collection {
+            ShellTaskCollection::All => self.lines.push_back(line.to_string()),
+            ShellTaskCollection::Tail(0) | ShellTaskCollection::Nothing => {}
+            ShellTaskCollection::Tail(n) => {
+                if self.lines.len() == n {
+                    self.lines.pop_front();
+                }
+                self.lines.push_back(line.to_string());
+            }
+        }
+    }
+
+    pub(crate) fn into_vec(self) -> Vec<String> {
+        self.lines.into()
+    }
+}
@@ -0,0 +1,44 @@
+use std::{fmt, process::ExitStatus, sync::Arc};
+
+/// A predicate that decides whether a finished task succeeded,
+/// given its exit status and the lines it printed to `stdout` and `stderr`.
+type SuccessPredicate = dyn Fn(&ExitStatus, &[String], &[String]) -> bool + Send + Sync + 'static;
+
+/// Decides whether the exit status of a [`ShellTask`] should be treated as a success.
+#[derive(Clone, Default)]
+pub(crate) enum SuccessCriteria {
+    /// Only an exit status of 0 is successful.
+    #[default]
+    ExitStatus,
+
+    /// Any of these exit codes are successful.
+    Codes(Vec<i32>),
+
+    /// A user-defined predicate decides.
+    Predicate(Arc<SuccessPredicate>),
+}
+
+impl SuccessCriteria {
+    pub(crate) fn is_success(
+        &self,
+        status: &ExitStatus,
+        stdout_lines: &[String],
+        stderr_lines: &[String],
+    ) -> bool {
+        match self {
+            SuccessCriteria::ExitStatus => status.success(),
+            SuccessCriteria::Codes(codes) => status.code().is_some_and(|c| codes.contains(&c)),
+            SuccessCriteria::Predicate(predicate) => predicate(status, stdout_lines, stderr_lines),
+        }
+    }
+}
+
+impl fmt::Debug for SuccessCriteria {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SuccessCriteria::ExitStatus => write!(f, "ExitStatus"),
+            SuccessCriteria::Codes(codes) => f.debug_tuple("Codes").field(codes).finish(),
+            SuccessCriteria::Predicate(_) => write!(f, "Predicate(..)"),
+        }
+    }
+}
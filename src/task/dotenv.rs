@@ -0,0 +1,336 @@
+//! A parser for the dotenv file format loaded by [`ShellTask::env_file`].
+
+#[cfg(doc)]
+use crate::ShellTask;
+
+use super::expand::{is_valid_name, parse_reference};
+
+/// Parses the contents of a dotenv file into a list of key/value pairs.
+///
+/// `${VAR}`, `${VAR:-default}`, and `$VAR` references in unquoted and double-quoted values are replaced with
+/// variables defined earlier in the file, falling back to `lookup`. Unknown variables without a default expand
+/// to an empty string. Errors are reported as the 1-based line number and a reason.
+pub(crate) fn parse<F>(
+    contents: &str,
+    lookup: F,
+) -> std::result::Result<Vec<(String, String)>, (usize, String)>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut vars: Vec<(String, String)> = Vec::new();
+    for (index, raw_line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // errors never include the text of the line, since values are often secrets
+        let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+        let (key, value) = line.split_once('=').ok_or_else(|| {
+            (
+                line_number,
+                "expected KEY=VALUE, but the line has no '='".to_string(),
+            )
+        })?;
+        let key = key.trim();
+        if !is_valid_name(key) {
+            return Err((
+                line_number,
+                format!("'{}' is not a valid variable name", key),
+            ));
+        }
+        let resolve = |name: &str| {
+            vars.iter()
+                .rev()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.to_string())
+                .or_else(|| lookup(name))
+        };
+        let value = value.trim();
+        // the value ends where the line does once trailing whitespace is trimmed
+        let value_start = raw_line.trim_end().len() - value.len();
+        let value = parse_value(value, resolve).map_err(|(offset, reason)| {
+            let column = raw_line[..value_start + offset].chars().count() + 1;
+            (line_number, format!("{} at column {}", reason, column))
+        })?;
+        vars.push((key.to_string(), value));
+    }
+    Ok(vars)
+}
+
+/// Parses a value, returning the byte offset in `value` where it is malformed and the reason on error.
+fn parse_value<F>(value: &str, resolve: F) -> std::result::Result<String, (usize, String)>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let offset = |rest: &str| value.len() - rest.len();
+
+    if let Some(rest) = value.strip_prefix('\'') {
+        let (quoted, trailing) = rest
+            .split_once('\'')
+            .ok_or_else(|| (0, "the single-quoted value is never closed".to_string()))?;
+        check_trailing(trailing).map_err(|reason| (offset(trailing.trim_start()), reason))?;
+        return Ok(quoted.to_string());
+    }
+
+    if let Some(rest) = value.strip_prefix('"') {
+        let unclosed = || (0, "the double-quoted value is never closed".to_string());
+        let mut unescaped = String::new();
+        let mut rest = rest;
+        loop {
+            let mut chars = rest.chars();
+            match chars.next() {
+                None => return Err(unclosed()),
+                Some('"') => {
+                    let trailing = chars.as_str();
+                    check_trailing(trailing)
+                        .map_err(|reason| (offset(trailing.trim_start()), reason))?;
+                    return Ok(unescaped);
+                }
+                Some('\\') => {
+                    match chars.next() {
+                        Some('n') => unescaped.push('\n'),
+                        Some('t') => unescaped.push('\t'),
+                        Some('r') => unescaped.push('\r'),
+                        Some(c @ ('"' | '\\' | '$')) => unescaped.push(c),
+                        Some(c) => {
+                            unescaped.push('\\');
+                            unescaped.push(c);
+                        }
+                        None => return Err(unclosed()),
+                    }
+                    rest = chars.as_str();
+                }
+                Some('$') => {
+                    let (expanded, len) = expand_reference(rest, &resolve)
+                        .map_err(|reason| (offset(rest), reason))?;
+                    unescaped.push_str(&expanded);
+                    rest = &rest[len..];
+                }
+                Some(c) => {
+                    unescaped.push(c);
+                    rest = chars.as_str();
+                }
+            }
+        }
+    }
+
+    // unquoted values end at the first comment that is preceded by whitespace
+    let unquoted = match value.find(" #").or_else(|| value.find("\t#")) {
+        Some(comment) => value[..comment].trim_end(),
+        None => value,
+    };
+    let mut interpolated = String::new();
+    let mut rest = unquoted;
+    while let Some(position) = rest.find('$') {
+        interpolated.push_str(&rest[..position]);
+        let reference = &rest[position..];
+        let (expanded, len) = expand_reference(reference, &resolve)
+            .map_err(|reason| (unquoted.len() - reference.len(), reason))?;
+        interpolated.push_str(&expanded);
+        rest = &reference[len..];
+    }
+    interpolated.push_str(rest);
+    Ok(interpolated)
+}
+
+/// Only whitespace and comments may follow a closing quote.
+fn check_trailing(trailing: &str) -> std::result::Result<(), String> {
+    let trailing = trailing.trim();
+    if trailing.is_empty() || trailing.starts_with('#') {
+        Ok(())
+    } else {
+        Err("unexpected text after the closing quote".to_string())
+    }
+}
+
+/// Expands the variable reference at the start of `value`, which starts with `$`, returning the expansion
+/// and how many bytes it replaced. Unset variables expand to an empty string, like in a shell.
+fn expand_reference<F>(value: &str, resolve: F) -> std::result::Result<(String, usize), String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let Some(reference) = parse_reference(value)? else {
+        return Ok(("$".to_string(), 1));
+    };
+    let value =
+        resolve(reference.name).filter(|value| reference.default.is_none() || !value.is_empty());
+    Ok((
+        value
+            .or_else(|| reference.default.map(str::to_string))
+            .unwrap_or_default(),
+        reference.len,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    fn no_lookup(_: &str) -> Option<String> {
+        None
+    }
+
+    fn parse_ok(contents: &str) -> Vec<(String, String)> {
+        parse(contents, no_lookup).expect("the contents should parse")
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn skips_blank_lines_and_comments() {
+        assert_eq!(
+            parse_ok("\n# a comment\n   \nA=1\n  # indented comment\n"),
+            pairs(&[("A", "1")])
+        );
+    }
+
+    #[test]
+    fn strips_export_prefix() {
+        assert_eq!(parse_ok("export A=1"), pairs(&[("A", "1")]));
+    }
+
+    #[test]
+    fn unquoted_values_end_at_comments() {
+        assert_eq!(
+            parse_ok("A=one two # comment\nB=x#not-a-comment\nC=tab\t# comment"),
+            pairs(&[("A", "one two"), ("B", "x#not-a-comment"), ("C", "tab")])
+        );
+    }
+
+    #[test]
+    fn single_quoted_values_are_literal() {
+        assert_eq!(
+            parse_ok(r"A='$HOME \n # not a comment'"),
+            pairs(&[("A", r"$HOME \n # not a comment")])
+        );
+    }
+
+    #[test]
+    fn double_quoted_values_unescape_backslashes() {
+        assert_eq!(
+            parse_ok(r#"A="line\nnext\ttab\r \"q\" \\ \$B \x""#),
+            pairs(&[("A", "line\nnext\ttab\r \"q\" \\ $B \\x")])
+        );
+    }
+
+    #[test]
+    fn comments_may_follow_closing_quotes() {
+        assert_eq!(
+            parse_ok("A='one' # comment\nB=\"two\"   # comment"),
+            pairs(&[("A", "one"), ("B", "two")])
+        );
+    }
+
+    #[test]
+    fn dollars_that_do_not_start_a_reference_are_kept() {
+        assert_eq!(
+            parse_ok("A=cost $5\nB=\"$\"\nC=end$"),
+            pairs(&[("A", "cost $5"), ("B", "$"), ("C", "end$")])
+        );
+    }
+
+    #[test]
+    fn references_resolve_earlier_variables() {
+        assert_eq!(
+            parse_ok("A=1\nB=${A}-$A\nC=\"${A}\"\nA=2\nD=$A"),
+            pairs(&[("A", "1"), ("B", "1-1"), ("C", "1"), ("A", "2"), ("D", "2")])
+        );
+    }
+
+    #[test]
+    fn references_fall_back_to_lookup() {
+        let vars = parse("A=${OUTER}\nB=$OUTER\nC=$MISSING", |name| {
+            (name == "OUTER").then(|| "outer".to_string())
+        })
+        .unwrap();
+        assert_eq!(vars, pairs(&[("A", "outer"), ("B", "outer"), ("C", "")]));
+    }
+
+    #[test]
+    fn references_use_defaults() {
+        assert_eq!(
+            parse_ok("EMPTY=\nA=${MISSING:-x}\nB=${EMPTY:-y}"),
+            pairs(&[("EMPTY", ""), ("A", "x"), ("B", "y")])
+        );
+    }
+
+    #[test]
+    fn reports_missing_equals_with_line_number() {
+        assert_eq!(
+            parse("A=1\n\nnot a pair", no_lookup).unwrap_err(),
+            (3, "expected KEY=VALUE, but the line has no '='".to_string())
+        );
+    }
+
+    #[test]
+    fn reports_invalid_keys_with_line_number() {
+        assert_eq!(
+            parse("A=1\n1A=2", no_lookup).unwrap_err(),
+            (2, "'1A' is not a valid variable name".to_string())
+        );
+    }
+
+    #[test]
+    fn reports_unclosed_quotes_with_line_and_column() {
+        assert_eq!(
+            parse("# comment\n  export A = 'open", no_lookup).unwrap_err(),
+            (
+                2,
+                "the single-quoted value is never closed at column 14".to_string()
+            )
+        );
+        assert_eq!(
+            parse("A=\"open\\\"", no_lookup).unwrap_err(),
+            (
+                1,
+                "the double-quoted value is never closed at column 3".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn reports_text_after_closing_quote_with_column() {
+        assert_eq!(
+            parse("A=1\nB=\"x\" y", no_lookup).unwrap_err(),
+            (
+                2,
+                "unexpected text after the closing quote at column 7".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn reports_malformed_references_with_column() {
+        assert_eq!(
+            parse("A=ok ${B", no_lookup).unwrap_err(),
+            (1, "'${' is never closed with '}' at column 6".to_string())
+        );
+        assert_eq!(
+            parse("A=\"é ${1}\"", no_lookup).unwrap_err(),
+            (
+                1,
+                "'${...}' does not contain a valid variable name at column 6".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn errors_do_not_include_values() {
+        for contents in [
+            "API_TOKEN abc123",
+            "API_TOKEN='abc123",
+            "API_TOKEN=\"abc123\" abc123",
+            "API_TOKEN=${abc123",
+            "API_TOKEN=${abc123 x}",
+        ] {
+            let (_, reason) = parse(contents, no_lookup).unwrap_err();
+            assert!(!reason.contains("abc123"), "{}", reason);
+        }
+    }
+}
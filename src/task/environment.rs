@@ -0,0 +1,107 @@
+use std::{
+    collections::HashMap,
+    env,
+    ffi::{OsStr, OsString},
+    fmt,
+    process::Command,
+};
+
+/// Which variables a [`ShellTask`] inherits from the environment of the current process.
+#[derive(Debug, Clone, Default)]
+enum EnvInheritance {
+    /// Every variable is inherited.
+    #[default]
+    All,
+
+    /// Only the listed variables are inherited.
+    Only(Vec<OsString>),
+
+    /// Nothing is inherited.
+    Nothing,
+}
+
+/// The environment a [`ShellTask`] runs in.
+#[derive(Clone, Default)]
+pub(crate) struct TaskEnv {
+    inheritance: EnvInheritance,
+
+    /// Explicitly configured variables, where [`None`] marks a removed variable.
+    vars: HashMap<OsString, Option<OsString>>,
+}
+
+impl TaskEnv {
+    pub(crate) fn set(&mut self, key: &OsStr, value: &OsStr) {
+        self.vars
+            .insert(key.to_os_string(), Some(value.to_os_string()));
+    }
+
+    pub(crate) fn remove(&mut self, key: &OsStr) {
+        self.vars.insert(key.to_os_string(), None);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.inheritance = EnvInheritance::Nothing;
+        self.vars.clear();
+    }
+
+    pub(crate) fn inherit_only(&mut self, keys: Vec<OsString>) {
+        self.inheritance = EnvInheritance::Only(keys);
+    }
+
+    /// Returns the value `key` will have in the environment of the task.
+    pub(crate) fn get(&self, key: &OsStr) -> Option<OsString> {
+        if let Some(value) = self.vars.get(key) {
+            return value.clone();
+        }
+        match &self.inheritance {
+            EnvInheritance::All => env::var_os(key),
+            EnvInheritance::Only(keys) if keys.iter().any(|k| k == key) => env::var_os(key),
+            EnvInheritance::Only(_) | EnvInheritance::Nothing => None,
+        }
+    }
+
+    /// Applies this environment to `command`.
+    pub(crate) fn apply(&self, command: &mut Command) {
+        match &self.inheritance {
+            EnvInheritance::All => {}
+            EnvInheritance::Only(keys) => {
+                command.env_clear();
+                for key in keys {
+                    if let Some(value) = env::var_os(key) {
+                        command.env(key, value);
+                    }
+                }
+            }
+            EnvInheritance::Nothing => {
+                command.env_clear();
+            }
+        }
+        for (key, value) in &self.vars {
+            match value {
+                Some(value) => command.env(key, value),
+                None => command.env_remove(key),
+            };
+        }
+    }
+}
+
+impl fmt::Debug for TaskEnv {
+    /// Only variable names are shown, since values often hold credentials.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut set: Vec<&OsString> = Vec::new();
+        let mut removed: Vec<&OsString> = Vec::new();
+        for (key, value) in &self.vars {
+            match value {
+                Some(_) => set.push(key),
+                None => removed.push(key),
+            }
+        }
+        set.sort();
+        removed.sort();
+        f.debug_struct("TaskEnv")
+            .field("inheritance", &self.inheritance)
+            .field("set", &set)
+            .field("removed", &removed)
+            .finish()
+    }
+}
@@ -0,0 +1,97 @@
+//! Expands variables and `~` in the words of a command for [`ShellTask::expand`], without invoking a shell.
+
+#[cfg(doc)]
+use crate::ShellTask;
+
+/// Expands `$VAR`, `${VAR}`, and `${VAR:-default}` in `word` using `lookup`, and a leading `~`
+/// (on its own or followed by `/`) using `home`. Returns the reason expansion failed on error.
+pub(crate) fn expand<F>(
+    word: &str,
+    lookup: F,
+    home: Option<String>,
+) -> std::result::Result<String, String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut expanded = String::new();
+    let mut rest = word;
+    if rest == "~" || rest.starts_with("~/") {
+        let home =
+            home.ok_or_else(|| "'~' cannot be expanded because HOME is not set".to_string())?;
+        expanded.push_str(&home);
+        rest = &rest[1..];
+    }
+
+    while let Some(position) = rest.find('$') {
+        expanded.push_str(&rest[..position]);
+        rest = &rest[position..];
+        let Some(reference) = parse_reference(rest)? else {
+            // a `$` that does not start a variable reference is kept as is
+            expanded.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+        let value =
+            lookup(reference.name).filter(|value| reference.default.is_none() || !value.is_empty());
+        match (value, reference.default) {
+            (Some(value), _) => expanded.push_str(&value),
+            (None, Some(default)) => expanded.push_str(default),
+            (None, None) => return Err(format!("'{}' is not set", &rest[..reference.len])),
+        }
+        rest = &rest[reference.len..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// A variable reference like `$VAR`, `${VAR}`, or `${VAR:-default}`.
+pub(crate) struct Reference<'a> {
+    /// The name of the variable.
+    pub(crate) name: &'a str,
+
+    /// The value to use when the variable is unset or empty, if any.
+    pub(crate) default: Option<&'a str>,
+
+    /// The length of the reference in bytes, including the `$`.
+    pub(crate) len: usize,
+}
+
+/// Parses the variable reference at the start of `text`, which must start with `$`.
+/// Returns [`None`] if the `$` does not start a reference, and the reason a braced reference is malformed on error.
+/// The reason never includes the text of the reference, since it may come from a file of secrets.
+pub(crate) fn parse_reference(text: &str) -> std::result::Result<Option<Reference<'_>>, String> {
+    let after_dollar = &text[1..];
+    if let Some(braced) = after_dollar.strip_prefix('{') {
+        let (reference, _) = braced
+            .split_once('}')
+            .ok_or_else(|| "'${' is never closed with '}'".to_string())?;
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+        if !is_valid_name(name) {
+            return Err("'${...}' does not contain a valid variable name".to_string());
+        }
+        return Ok(Some(Reference {
+            name,
+            default,
+            len: reference.len() + 3,
+        }));
+    }
+    let name_len = after_dollar
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(after_dollar.len());
+    let name = &after_dollar[..name_len];
+    Ok(is_valid_name(name).then_some(Reference {
+        name,
+        default: None,
+        len: name_len + 1,
+    }))
+}
+
+/// Variable names must start with a letter or underscore and only contain letters, digits, and underscores.
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
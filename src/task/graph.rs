@@ -0,0 +1,372 @@
+use std::{
+    collections::{BTreeSet, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use crossbeam_channel::unbounded;
+
+use crate::{Error, Result, ShellTask, ShellTaskBehavior, ShellTaskGraphLog, ShellTaskOutput};
+
+/// Hands out a unique tag to every [`ShellTaskGraph`], so that ids from one graph are not mistaken for another's.
+static NEXT_GRAPH: AtomicU64 = AtomicU64::new(0);
+
+/// Identifies a task added to a [`ShellTaskGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ShellTaskId {
+    graph: u64,
+    index: usize,
+}
+
+impl ShellTaskId {
+    /// Returns the index of the task in the order tasks were added to the graph, starting at 0.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+/// A [`ShellTaskGraph`] runs tasks that depend on each other, starting each task as soon as
+/// every task it runs after has completed successfully.
+///
+/// If a task fails or returns early, every task that depends on it, directly or indirectly, is skipped.
+/// Tasks that do not depend on it keep running.
+///
+/// # Example
+///
+/// ```
+/// use shell_candy::{ShellTask, ShellTaskBehavior, ShellTaskGraph, ShellTaskGraphStatus};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+///     let mut graph = ShellTaskGraph::new();
+///     let fetch = graph.add(ShellTask::new("echo fetch")?).id();
+///     let build = graph.add(ShellTask::new("false")?).after(fetch).id();
+///     let lint = graph.add(ShellTask::new("echo lint")?).after(fetch).id();
+///     let test = graph.add(ShellTask::new("echo test")?).after(build).after(lint).id();
+///
+///     let report = graph.run(|line| {
+///         eprintln!("[{}] {:?}", line.task.index(), line.log);
+///         ShellTaskBehavior::<()>::Passthrough
+///     })?;
+///     assert!(!report.is_success());
+///     assert!(matches!(report[lint].status, ShellTaskGraphStatus::Finished(Ok(_))));
+///     assert!(matches!(report[build].status, ShellTaskGraphStatus::Finished(Err(_))));
+///     assert!(matches!(report[test].status, ShellTaskGraphStatus::Skipped { dependency } if dependency == build));
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ShellTaskGraph {
+    graph: u64,
+    tasks: Vec<ShellTask>,
+    dependencies: Vec<BTreeSet<ShellTaskId>>,
+    max_concurrency: Option<usize>,
+}
+
+/// A task in a [`ShellTaskGraph`], returned by [`ShellTaskGraph::add`] and [`ShellTaskGraph::node`]
+/// so that its dependencies can be declared.
+#[derive(Debug)]
+pub struct ShellTaskGraphNode<'a> {
+    graph: &'a mut ShellTaskGraph,
+    id: ShellTaskId,
+}
+
+impl ShellTaskGraphNode<'_> {
+    /// Makes this task wait until `dependency` has completed successfully.
+    ///
+    /// If `dependency` belongs to another graph, [`ShellTaskGraph::run`] returns [`Error::InvalidTask`].
+    pub fn after(self, dependency: ShellTaskId) -> Self {
+        self.graph.dependencies[self.id.index].insert(dependency);
+        self
+    }
+
+    /// Returns the id of this task.
+    pub fn id(&self) -> ShellTaskId {
+        self.id
+    }
+}
+
+/// What happened to one task of a [`ShellTaskGraph`].
+#[derive(Debug)]
+pub enum ShellTaskGraphStatus<T> {
+    /// The task ran, and this is what [`ShellTask::run`] returned for it.
+    Finished(Result<ShellTaskOutput<T>>),
+
+    /// The task did not run because `dependency` failed, returned early, or was skipped itself.
+    Skipped {
+        /// The dependency that kept this task from running.
+        dependency: ShellTaskId,
+    },
+}
+
+/// The outcome of one task of a [`ShellTaskGraph`].
+#[derive(Debug)]
+pub struct ShellTaskGraphResult<T> {
+    /// The id of the task.
+    pub id: ShellTaskId,
+
+    /// The [`ShellTask::descriptor`] of the task.
+    pub descriptor: String,
+
+    /// What happened to the task.
+    pub status: ShellTaskGraphStatus<T>,
+}
+
+/// [`ShellTaskGraphReport`] is returned by [`ShellTaskGraph::run`] and describes every task.
+/// It can be indexed by [`ShellTaskId`].
+#[derive(Debug)]
+pub struct ShellTaskGraphReport<T> {
+    /// The outcome of each task, in the order tasks were added.
+    pub results: Vec<ShellTaskGraphResult<T>>,
+}
+
+impl<T> ShellTaskGraphReport<T> {
+    /// Returns true if every task completed successfully without returning early.
+    pub fn is_success(&self) -> bool {
+        self.results.iter().all(|result| {
+            matches!(
+                result.status,
+                ShellTaskGraphStatus::Finished(Ok(ShellTaskOutput::CompleteOutput { .. }))
+            )
+        })
+    }
+}
+
+impl<T> std::ops::Index<ShellTaskId> for ShellTaskGraphReport<T> {
+    type Output = ShellTaskGraphResult<T>;
+
+    fn index(&self, id: ShellTaskId) -> &Self::Output {
+        let result = &self.results[id.index];
+        assert_eq!(result.id, id, "{:?} is not part of this graph", id);
+        result
+    }
+}
+
+impl Default for ShellTaskGraph {
+    fn default() -> Self {
+        Self {
+            graph: NEXT_GRAPH.fetch_add(1, Ordering::Relaxed),
+            tasks: Vec::new(),
+            dependencies: Vec::new(),
+            max_concurrency: None,
+        }
+    }
+}
+
+impl ShellTaskGraph {
+    /// Create a new, empty [`ShellTaskGraph`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a task to the graph. Declare what it runs after with [`ShellTaskGraphNode::after`].
+    pub fn add(&mut self, task: ShellTask) -> ShellTaskGraphNode<'_> {
+        self.tasks.push(task);
+        self.dependencies.push(BTreeSet::new());
+        let id = self.id(self.tasks.len() - 1);
+        ShellTaskGraphNode { graph: self, id }
+    }
+
+    /// Returns a task that was already added, so that more dependencies can be declared for it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` does not belong to this graph.
+    pub fn node(&mut self, id: ShellTaskId) -> ShellTaskGraphNode<'_> {
+        assert!(
+            id.graph == self.graph && id.index < self.tasks.len(),
+            "{:?} is not part of this graph",
+            id
+        );
+        ShellTaskGraphNode { graph: self, id }
+    }
+
+    /// Limits how many tasks run at the same time. By default, every task starts as soon as its
+    /// dependencies are done. A limit of 0 is treated as 1.
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = Some(max_concurrency.max(1));
+        self
+    }
+
+    /// Run every task in a [`ShellTaskGraph`], applying the log handler to every line.
+    ///
+    /// The log handler is shared by every task, so it may be called from several threads at once.
+    /// Returning [`ShellTaskBehavior::EarlyReturn`] stops the task that printed the line
+    /// and skips every task that depends on it.
+    ///
+    /// Returns [`Error::DependencyCycle`] without running anything if the tasks depend on each other
+    /// in a cycle, or [`Error::InvalidTask`] if a task runs after a task from another graph.
+    pub fn run<F, T>(&self, log_handler: F) -> Result<ShellTaskGraphReport<T>>
+    where
+        F: Fn(ShellTaskGraphLog) -> ShellTaskBehavior<T> + Send + Sync + 'static,
+        T: Send + Sync + 'static,
+    {
+        self.validate()?;
+
+        let log_handler = Arc::new(log_handler);
+        let max_concurrency = self.max_concurrency.unwrap_or(usize::MAX);
+        let mut dependents = vec![Vec::new(); self.tasks.len()];
+        for (task, dependencies) in self.dependencies.iter().enumerate() {
+            for dependency in dependencies {
+                dependents[dependency.index].push(task);
+            }
+        }
+        let mut waiting_on: Vec<usize> = self.dependencies.iter().map(BTreeSet::len).collect();
+        let mut statuses: Vec<Option<ShellTaskGraphStatus<T>>> =
+            self.tasks.iter().map(|_| None).collect();
+        let mut ready: VecDeque<usize> = (0..self.tasks.len())
+            .filter(|task| waiting_on[*task] == 0)
+            .collect();
+
+        thread::scope(|scope| {
+            let (done_sender, done_receiver) = unbounded();
+            let mut running = 0;
+            loop {
+                while running < max_concurrency {
+                    let Some(index) = ready.pop_front() else {
+                        break;
+                    };
+                    let task = &self.tasks[index];
+                    let log_handler = log_handler.clone();
+                    let done_sender = done_sender.clone();
+                    scope.spawn(move || {
+                        let id = self.id(index);
+                        // a panic is reported as the task's result, so the scheduler always hears back
+                        let result = task.catch_panic(|| {
+                            task.run(move |log| log_handler(ShellTaskGraphLog { task: id, log }))
+                        });
+                        // the scheduler keeps its receiver until every task it started is done
+                        let _ = done_sender.send((index, result));
+                    });
+                    running += 1;
+                }
+                if running == 0 {
+                    break;
+                }
+
+                let (index, result) = done_receiver
+                    .recv()
+                    .expect("the scheduler holds a sender, so the channel never disconnects");
+                running -= 1;
+                let succeeded = matches!(result, Ok(ShellTaskOutput::CompleteOutput { .. }));
+                statuses[index] = Some(ShellTaskGraphStatus::Finished(result));
+                if succeeded {
+                    for &dependent in &dependents[index] {
+                        waiting_on[dependent] -= 1;
+                        if waiting_on[dependent] == 0 && statuses[dependent].is_none() {
+                            ready.push_back(dependent);
+                        }
+                    }
+                } else {
+                    self.skip_dependents(index, &dependents, &mut statuses);
+                }
+            }
+        });
+
+        Ok(ShellTaskGraphReport {
+            results: statuses
+                .into_iter()
+                .enumerate()
+                .map(|(index, status)| ShellTaskGraphResult {
+                    id: self.id(index),
+                    descriptor: self.tasks[index].descriptor(),
+                    status: status.expect("every task either finishes or is skipped"),
+                })
+                .collect(),
+        })
+    }
+
+    /// Makes sure every dependency belongs to this graph and that there are no cycles.
+    fn validate(&self) -> Result<()> {
+        for (task, dependencies) in self.tasks.iter().zip(&self.dependencies) {
+            if dependencies
+                .iter()
+                .any(|dependency| dependency.graph != self.graph)
+            {
+                return Err(Error::InvalidTask {
+                    task: task.descriptor(),
+                    reason: "it runs after a task that is not part of this graph".to_string(),
+                });
+            }
+        }
+
+        // repeatedly remove tasks whose dependencies have all been removed; whatever is left is part of,
+        // or depends on, a cycle
+        let mut remaining: BTreeSet<usize> = (0..self.tasks.len()).collect();
+        loop {
+            let removable: Vec<usize> = remaining
+                .iter()
+                .copied()
+                .filter(|task| {
+                    self.dependencies[*task]
+                        .iter()
+                        .all(|dependency| !remaining.contains(&dependency.index))
+                })
+                .collect();
+            if removable.is_empty() {
+                break;
+            }
+            for task in removable {
+                remaining.remove(&task);
+            }
+        }
+        let Some(&start) = remaining.iter().next() else {
+            return Ok(());
+        };
+
+        // every remaining task has a remaining dependency, so following them must eventually revisit a task
+        let mut path = vec![start];
+        loop {
+            let current = path[path.len() - 1];
+            let next = self.dependencies[current]
+                .iter()
+                .map(|dependency| dependency.index)
+                .find(|dependency| remaining.contains(dependency))
+                .expect("every remaining task has a remaining dependency");
+            if let Some(position) = path.iter().position(|task| *task == next) {
+                // the path follows dependencies backwards, so reverse it to list tasks in the order they would run,
+                // starting with the task that was added first
+                let mut cycle: Vec<usize> = path[position..].iter().rev().copied().collect();
+                if let Some((first, _)) = cycle.iter().enumerate().min_by_key(|(_, task)| **task) {
+                    cycle.rotate_left(first);
+                }
+                let cycle = cycle
+                    .into_iter()
+                    .map(|task| self.tasks[task].descriptor())
+                    .collect();
+                return Err(Error::DependencyCycle { cycle });
+            }
+            path.push(next);
+        }
+    }
+
+    /// Returns the id of the task at `index` in this graph.
+    fn id(&self, index: usize) -> ShellTaskId {
+        ShellTaskId {
+            graph: self.graph,
+            index,
+        }
+    }
+
+    /// Marks every task that depends on `index`, directly or indirectly, as skipped.
+    fn skip_dependents<T>(
+        &self,
+        index: usize,
+        dependents: &[Vec<usize>],
+        statuses: &mut [Option<ShellTaskGraphStatus<T>>],
+    ) {
+        let mut to_skip = vec![index];
+        while let Some(dependency) = to_skip.pop() {
+            for &dependent in &dependents[dependency] {
+                if statuses[dependent].is_none() {
+                    statuses[dependent] = Some(ShellTaskGraphStatus::Skipped {
+                        dependency: self.id(dependency),
+                    });
+                    to_skip.push(dependent);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    num::NonZeroUsize,
+    panic,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::{Result, ShellTask, ShellTaskBehavior, ShellTaskGroupLog, ShellTaskOutput};
+
+#[cfg(doc)]
+use crate::Error;
+
+use super::runner;
+
+/// A [`ShellTaskGroup`] runs independent tasks in parallel, at most [`ShellTaskGroup::max_concurrency`] at a time.
+///
+/// Tasks start in the order they were added. With [`ShellTaskGroup::fail_fast`], the first task that fails
+/// or returns early kills every other running task and keeps the rest from starting.
+///
+/// # Example
+///
+/// ```
+/// use shell_candy::{ShellTask, ShellTaskBehavior, ShellTaskGroup};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+///     let output = ShellTaskGroup::new([ShellTask::new("echo one")?, ShellTask::new("echo two")?])
+///         .task(ShellTask::new("false")?)
+///         .max_concurrency(2)
+///         .run(|line| {
+///             eprintln!("[{}] {:?}", line.task, line.log);
+///             ShellTaskBehavior::<()>::Passthrough
+///         });
+///     assert!(!output.is_success());
+///     assert!(output.get("echo one").unwrap().is_ok());
+///     assert!(output.get("false").unwrap().is_err());
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ShellTaskGroup {
+    tasks: Vec<ShellTask>,
+    max_concurrency: Option<usize>,
+    fail_fast: bool,
+}
+
+/// The outcome of one task of a [`ShellTaskGroup`].
+#[derive(Debug)]
+pub struct ShellTaskGroupResult<T> {
+    /// The [`ShellTask::descriptor`] of the task.
+    pub descriptor: String,
+
+    /// What [`ShellTask::run`] returned for the task, or [`Error::Cancelled`]
+    /// if it was killed or never started because of [`ShellTaskGroup::fail_fast`].
+    pub result: Result<ShellTaskOutput<T>>,
+}
+
+/// [`ShellTaskGroupOutput`] is returned by [`ShellTaskGroup::run`] and describes every task.
+#[derive(Debug)]
+pub struct ShellTaskGroupOutput<T> {
+    /// The outcome of each task, in the order tasks were added.
+    pub results: Vec<ShellTaskGroupResult<T>>,
+}
+
+impl<T> ShellTaskGroupOutput<T> {
+    /// Returns the result of the first task with the given [`ShellTask::descriptor`].
+    pub fn get(&self, descriptor: &str) -> Option<&Result<ShellTaskOutput<T>>> {
+        self.results
+            .iter()
+            .find(|result| result.descriptor == descriptor)
+            .map(|result| &result.result)
+    }
+
+    /// Returns true if every task completed successfully without returning early.
+    pub fn is_success(&self) -> bool {
+        self.results
+            .iter()
+            .all(|result| matches!(result.result, Ok(ShellTaskOutput::CompleteOutput { .. })))
+    }
+}
+
+impl ShellTaskGroup {
+    /// Create a new [`ShellTaskGroup`] from its tasks.
+    pub fn new<I>(tasks: I) -> Self
+    where
+        I: IntoIterator<Item = ShellTask>,
+    {
+        Self {
+            tasks: tasks.into_iter().collect(),
+            max_concurrency: None,
+            fail_fast: false,
+        }
+    }
+
+    /// Adds a task to the group.
+    pub fn task(mut self, task: ShellTask) -> Self {
+        self.tasks.push(task);
+        self
+    }
+
+    /// Limits how many tasks run at the same time. Defaults to [`std::thread::available_parallelism`].
+    /// A limit of 0 is treated as 1.
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = Some(max_concurrency.max(1));
+        self
+    }
+
+    /// Kills every running task, and skips every task that has not started, as soon as one task
+    /// fails or returns early. The affected tasks report [`Error::Cancelled`].
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Run every task in a [`ShellTaskGroup`], applying the log handler to every line.
+    ///
+    /// The log handler is shared by every task, so it may be called from several threads at once.
+    /// Returning [`ShellTaskBehavior::EarlyReturn`] only stops the task that printed the line,
+    /// unless [`ShellTaskGroup::fail_fast`] is set.
+    pub fn run<F, T>(&self, log_handler: F) -> ShellTaskGroupOutput<T>
+    where
+        F: Fn(ShellTaskGroupLog) -> ShellTaskBehavior<T> + Send + Sync + 'static,
+        T: Send + Sync + 'static,
+    {
+        let log_handler = Arc::new(log_handler);
+        let (cancellation_handle, cancellation) = runner::cancellation();
+        let cancellation_handle = Mutex::new(cancellation_handle);
+        let next_task = AtomicUsize::new(0);
+        let workers = self
+            .max_concurrency
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, NonZeroUsize::get))
+            .min(self.tasks.len());
+
+        let mut results: Vec<(usize, Result<ShellTaskOutput<T>>)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..workers)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut results = Vec::new();
+                        // each worker claims the next task that has not started until none are left
+                        loop {
+                            let index = next_task.fetch_add(1, Ordering::SeqCst);
+                            let Some(task) = self.tasks.get(index) else {
+                                return results;
+                            };
+                            let result = if cancellation.is_cancelled() {
+                                Err(task.cancelled())
+                            } else {
+                                let log_handler = log_handler.clone();
+                                task.catch_panic(|| {
+                                    task.run_cancellable(
+                                        move |log| {
+                                            log_handler(ShellTaskGroupLog { task: index, log })
+                                        },
+                                        Some(&cancellation),
+                                    )
+                                })
+                            };
+                            let completed =
+                                matches!(result, Ok(ShellTaskOutput::CompleteOutput { .. }));
+                            if self.fail_fast && !completed {
+                                if let Ok(mut handle) = cancellation_handle.lock() {
+                                    handle.cancel();
+                                }
+                            }
+                            results.push((index, result));
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                // panics while running a task are caught per task, so a worker that panics anyway
+                // has a bug of its own that should not be hidden
+                .flat_map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|payload| panic::resume_unwind(payload))
+                })
+                .collect()
+        });
+        results.sort_by_key(|(index, _)| *index);
+
+        ShellTaskGroupOutput {
+            results: results
+                .into_iter()
+                .map(|(index, result)| ShellTaskGroupResult {
+                    descriptor: self.tasks[index].descriptor(),
+                    result,
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::{fmt, sync::Arc};
+
+use regex::{Captures, Regex};
+
+use crate::{Error, Result, ShellTaskBehavior, ShellTaskLog, ShellTaskStream};
+
+#[cfg(doc)]
+use crate::ShellTask;
+
+/// Turns the captures of a matching line into the value a handler returns early with.
+type Capture<T> = dyn Fn(&Captures<'_>) -> T + Send + Sync + 'static;
+
+/// What a [`ShellTaskMatcher`] does when a rule matches a line. Created with [`fail`] or [`succeed`].
+#[derive(Debug, Clone)]
+pub enum ShellTaskMatcherAction<T> {
+    /// Return early with an error made of this message and the line that matched.
+    Fail(String),
+
+    /// Return early with this value.
+    Succeed(T),
+}
+
+/// Returns early with an error made of `message` and the line that matched, like `compile error: error[E0308]: ...`.
+pub fn fail<T, S>(message: S) -> ShellTaskMatcherAction<T>
+where
+    S: Into<String>,
+{
+    ShellTaskMatcherAction::Fail(message.into())
+}
+
+/// Returns early with `value`.
+pub fn succeed<T>(value: T) -> ShellTaskMatcherAction<T> {
+    ShellTaskMatcherAction::Succeed(value)
+}
+
+/// What a rule does when it matches.
+enum Outcome<T> {
+    Action(ShellTaskMatcherAction<T>),
+    Capture(Arc<Capture<T>>),
+}
+
+/// A pattern, the streams it applies to, and what to do when it matches.
+struct Rule<T> {
+    stream: Option<ShellTaskStream>,
+    regex: Regex,
+    outcome: Outcome<T>,
+}
+
+impl<T> fmt::Debug for Rule<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Rule")
+            .field("stream", &self.stream)
+            .field("regex", &self.regex.as_str())
+            .finish_non_exhaustive()
+    }
+}
+
+/// [`ShellTaskMatcher`] builds a log handler out of regular expressions, for the common case of
+/// returning early when a task prints a line that matches a pattern.
+///
+/// Rules are checked in the order they were added, and the first rule that matches a line decides
+/// what happens. Lines that match no rule pass through. Invalid patterns are reported by [`ShellTaskMatcher::build`].
+///
+/// # Example
+///
+/// ```
+/// use shell_candy::{fail, ShellTask, ShellTaskMatcher, ShellTaskOutput};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+///     let handler = ShellTaskMatcher::new()
+///         .on_stderr(r"error\[E\d+\]", fail("compile error"))
+///         .capture_stdout(r"listening on (\d+)", |caps| caps[1].parse::<u16>().unwrap())
+///         .build()?;
+///     let task = ShellTask::shell("echo 'listening on 8080'; sleep 5")?;
+///     let ShellTaskOutput::EarlyReturn { return_value, .. } = task.run(handler)? else {
+///         unreachable!("the task prints a port before it exits")
+///     };
+///     assert_eq!(return_value, 8080);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ShellTaskMatcher<T> {
+    rules: Result<Vec<Rule<T>>>,
+}
+
+impl<T> Default for ShellTaskMatcher<T> {
+    fn default() -> Self {
+        Self {
+            rules: Ok(Vec::new()),
+        }
+    }
+}
+
+impl<T> ShellTaskMatcher<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    /// Create a new [`ShellTaskMatcher`] without any rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies `action` when a line printed to `stdout` matches `pattern`.
+    pub fn on_stdout(self, pattern: &str, action: ShellTaskMatcherAction<T>) -> Self {
+        self.rule(
+            Some(ShellTaskStream::Stdout),
+            pattern,
+            Outcome::Action(action),
+        )
+    }
+
+    /// Applies `action` when a line printed to `stderr` matches `pattern`.
+    pub fn on_stderr(self, pattern: &str, action: ShellTaskMatcherAction<T>) -> Self {
+        self.rule(
+            Some(ShellTaskStream::Stderr),
+            pattern,
+            Outcome::Action(action),
+        )
+    }
+
+    /// Applies `action` when a line printed to either stream matches `pattern`.
+    pub fn on_line(self, pattern: &str, action: ShellTaskMatcherAction<T>) -> Self {
+        self.rule(None, pattern, Outcome::Action(action))
+    }
+
+    /// Returns early with the value `capture` makes out of the captures of `pattern`
+    /// when a line printed to `stdout` matches it.
+    pub fn capture_stdout<F>(self, pattern: &str, capture: F) -> Self
+    where
+        F: Fn(&Captures<'_>) -> T + Send + Sync + 'static,
+    {
+        self.rule(
+            Some(ShellTaskStream::Stdout),
+            pattern,
+            Outcome::Capture(Arc::new(capture)),
+        )
+    }
+
+    /// Returns early with the value `capture` makes out of the captures of `pattern`
+    /// when a line printed to `stderr` matches it.
+    pub fn capture_stderr<F>(self, pattern: &str, capture: F) -> Self
+    where
+        F: Fn(&Captures<'_>) -> T + Send + Sync + 'static,
+    {
+        self.rule(
+            Some(ShellTaskStream::Stderr),
+            pattern,
+            Outcome::Capture(Arc::new(capture)),
+        )
+    }
+
+    /// Returns a log handler for [`ShellTask::run`] that applies every rule, or the first
+    /// [`Error::InvalidPattern`] encountered while adding rules.
+    pub fn build(
+        self,
+    ) -> Result<impl Fn(ShellTaskLog) -> ShellTaskBehavior<T> + Send + Sync + 'static> {
+        let rules = self.rules?;
+        Ok(move |log: ShellTaskLog| {
+            let (stream, line) = match &log {
+                ShellTaskLog::Stdout(line) => (ShellTaskStream::Stdout, line),
+                ShellTaskLog::Stderr(line) => (ShellTaskStream::Stderr, line),
+            };
+            for rule in &rules {
+                if rule.stream.is_some_and(|rule_stream| rule_stream != stream) {
+                    continue;
+                }
+                let Some(captures) = rule.regex.captures(line) else {
+                    continue;
+                };
+                return ShellTaskBehavior::EarlyReturn(match &rule.outcome {
+                    Outcome::Action(ShellTaskMatcherAction::Fail(message)) => {
+                        Err(format!("{}: {}", message, line).into())
+                    }
+                    Outcome::Action(ShellTaskMatcherAction::Succeed(value)) => Ok(value.clone()),
+                    Outcome::Capture(capture) => Ok(capture(&captures)),
+                });
+            }
+            ShellTaskBehavior::Passthrough
+        })
+    }
+
+    /// Compiles `pattern` and adds a rule for it if no errors have been encountered yet.
+    fn rule(mut self, stream: Option<ShellTaskStream>, pattern: &str, outcome: Outcome<T>) -> Self {
+        if let Ok(rules) = &mut self.rules {
+            match Regex::new(pattern) {
+                Ok(regex) => rules.push(Rule {
+                    stream,
+                    regex,
+                    outcome,
+                }),
+                Err(source) => {
+                    self.rules = Err(Error::InvalidPattern {
+                        pattern: pattern.to_string(),
+                        source,
+                    })
+                }
+            }
+        }
+        self
+    }
+}
@@ -1,46 +1,131 @@
 use std::{
-    collections::HashMap,
+    any::Any,
     env,
-    ffi::{OsStr, OsString},
+    ffi::OsStr,
+    fmt, fs,
+    panic::{self, AssertUnwindSafe, Location},
     path::{Path, PathBuf},
-    process::Command,
-    sync::{Arc, Mutex},
-    time::Duration,
+    process::{Command, ExitStatus},
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
+#[cfg(unix)]
+use std::os::unix::process::ExitStatusExt;
+
 use crate::{Error, Result, ShellTaskLog};
-use crossbeam_channel::{unbounded, Receiver, Sender};
+use crossbeam_channel::unbounded;
 
 mod behavior;
+mod builder;
+mod collection;
+mod criteria;
+mod dotenv;
+mod environment;
+mod expand;
+mod graph;
+mod group;
+#[cfg(feature = "regex")]
+mod matcher;
 mod output;
+mod pipeline;
+mod printer;
+mod process;
+mod resolve;
+mod retry;
 mod runner;
+mod script;
+mod sequence;
+mod service;
+mod stdin;
+#[cfg(feature = "semver")]
+mod version;
 
 pub use behavior::ShellTaskBehavior;
+pub use builder::ShellTaskBuilder;
+pub use collection::ShellTaskCollection;
+pub use graph::{
+    ShellTaskGraph, ShellTaskGraphNode, ShellTaskGraphReport, ShellTaskGraphResult,
+    ShellTaskGraphStatus, ShellTaskId,
+};
+pub use group::{ShellTaskGroup, ShellTaskGroupOutput, ShellTaskGroupResult};
+#[cfg(feature = "regex")]
+pub use matcher::{fail, succeed, ShellTaskMatcher, ShellTaskMatcherAction};
 pub use output::ShellTaskOutput;
-use runner::ShellTaskRunner;
+pub use pipeline::ShellPipeline;
+pub use printer::ShellTaskPrinter;
+pub use retry::{RetryBackoff, RetryPolicy, ShellTaskRetry, ShellTaskRetryOutput};
+pub use sequence::{
+    ShellTaskCondition, ShellTaskSequence, ShellTaskSequenceOutput, ShellTaskSequenceStep,
+};
+pub use service::{ShellTaskReadiness, ShellTaskService};
+pub use stdin::ShellTaskStdin;
+
+use collection::CollectedLines;
+use criteria::SuccessCriteria;
+use environment::TaskEnv;
+use runner::{Cancellation, LogEvent, LogStream, ShellTaskRunner};
+use script::ScriptFile;
 
 /// A [`ShellTask`] runs commands and provides a passthrough log handler
 /// for each log line.
-#[derive(Debug)]
+#[derive(Clone)]
 pub struct ShellTask {
     bin: String,
     args: Vec<String>,
     current_dir: PathBuf,
-    envs: HashMap<OsString, OsString>,
+    env: TaskEnv,
     full_command: String,
-    log_sender: Sender<ShellTaskLog>,
-    log_receiver: Receiver<ShellTaskLog>,
+    failure_output_tail: Option<usize>,
+    success_criteria: SuccessCriteria,
+    shell: Option<String>,
+    script: Option<Arc<ScriptFile>>,
+    expand: bool,
+    secrets: Vec<String>,
+    stdin: ShellTaskStdin,
+    timeout: Option<Duration>,
+    collection: ShellTaskCollection,
+    process_group: bool,
+    die_with_parent: bool,
+}
+
+impl fmt::Debug for ShellTask {
+    /// Arguments, environment values, `stdin` and the secrets themselves may hold credentials,
+    /// so only the redacted [`ShellTask::descriptor`] and settings that cannot leak them are shown.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stdin = match &self.stdin {
+            ShellTaskStdin::Bytes(bytes) => format!("Bytes({} bytes)", bytes.len()),
+            stdin => format!("{:?}", stdin),
+        };
+        f.debug_struct("ShellTask")
+            .field("descriptor", &self.descriptor())
+            .field("current_dir", &self.current_dir)
+            .field("env", &self.env)
+            .field("shell", &self.shell)
+            .field("script", &self.script)
+            .field("expand", &self.expand)
+            .field("secrets", &format_args!("{} redacted", self.secrets.len()))
+            .field("stdin", &format_args!("{}", stdin))
+            .field("timeout", &self.timeout)
+            .field("collection", &self.collection)
+            .field("failure_output_tail", &self.failure_output_tail)
+            .field("success_criteria", &self.success_criteria)
+            .field("process_group", &self.process_group)
+            .field("die_with_parent", &self.die_with_parent)
+            .finish()
+    }
 }
 
 impl ShellTask {
     /// Create a new [`ShellTask`] with a log line handler.
+    ///
+    /// The binary is not looked up until the task is run, so it is resolved against
+    /// the `PATH` and directory configured with [`ShellTask::env`] and [`ShellTask::current_dir`].
     pub fn new(command: &str) -> Result<Self> {
-        let current_dir =
-            env::current_dir().map_err(|source| Error::CouldNotFindCurrentDirectory { source })?;
         let command = command.to_string();
         let args: Vec<&str> = command.split(' ').collect();
         let (bin, args) = match args.len() {
-            0 => Err(Error::InvalidTask {
+            _ if args[0].is_empty() => Err(Error::InvalidTask {
                 task: command.to_string(),
                 reason: "an empty string is not a command".to_string(),
             }),
@@ -48,23 +133,135 @@ impl ShellTask {
             _ => Ok((args[0], Vec::from_iter(args[1..].iter()))),
         }?;
 
-        if which::which(bin).is_err() {
-            Err(Error::InvalidTask {
-                task: command.to_string(),
-                reason: format!("'{}' is not installed on this machine", &bin),
-            })
-        } else {
-            let (log_sender, log_receiver) = unbounded();
-            Ok(Self {
-                bin: bin.to_string(),
-                args: args.iter().map(|s| s.to_string()).collect(),
-                full_command: command,
-                envs: HashMap::new(),
-                current_dir,
-                log_sender,
-                log_receiver,
-            })
+        Self::from_parts(
+            bin.to_string(),
+            args.iter().map(|s| s.to_string()).collect(),
+            command.to_string(),
+        )
+    }
+
+    /// Create a new [`ShellTask`] that runs `script` with `sh -c`, so pipes, redirects, and globs work
+    /// like they do in a terminal. [`ShellTask::descriptor`] returns the script itself.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use shell_candy::{ShellTask, ShellTaskBehavior, ShellTaskOutput};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    ///     let task = ShellTask::shell("echo 'hello world' | tr a-z A-Z")?;
+    ///     assert_eq!(task.descriptor(), "echo 'hello world' | tr a-z A-Z");
+    ///     let ShellTaskOutput::CompleteOutput { stdout_lines, .. } =
+    ///         task.run(|_| ShellTaskBehavior::<()>::Passthrough)?
+    ///     else {
+    ///         unreachable!("the log handler never returns early")
+    ///     };
+    ///     assert_eq!(stdout_lines, vec!["HELLO WORLD".to_string()]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn shell(script: &str) -> Result<Self> {
+        Self::shell_with("sh -c", script)
+    }
+
+    /// Create a new [`ShellTask`] that runs `script` with a custom shell command, such as `bash -eo pipefail -c`.
+    /// The script is passed to the shell as its final argument.
+    ///
+    /// Failures the shell reports itself, like a command that does not exist or a syntax error,
+    /// are reported as [`Error::ShellFailure`] when the shell's diagnostic is among the collected `stderr` lines.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use shell_candy::{Error, ShellTask, ShellTaskBehavior};
+    ///
+    /// let error = ShellTask::shell_with("bash -eo pipefail -c", "definitely-not-a-command | cat")
+    ///     .unwrap()
+    ///     .run(|_| ShellTaskBehavior::<()>::Passthrough)
+    ///     .unwrap_err();
+    /// assert!(matches!(error, Error::ShellFailure { .. }));
+    /// ```
+    pub fn shell_with(shell: &str, script: &str) -> Result<Self> {
+        let mut args: Vec<String> = shell.split(' ').map(|s| s.to_string()).collect();
+        if args[0].is_empty() {
+            return Err(Error::InvalidTask {
+                task: script.to_string(),
+                reason: "an empty string is not a shell".to_string(),
+            });
+        }
+        let bin = args.remove(0);
+        args.push(script.to_string());
+        let mut task = Self::from_parts(bin, args, script.to_string())?;
+        task.shell = Some(shell.to_string());
+        Ok(task)
+    }
+
+    /// Create a new [`ShellTask`] that writes a multi-line script to a private temporary file
+    /// and runs it with `interpreter`, like `bash`, `python3`, or `node`. The file is removed when the
+    /// [`ShellTask`] is dropped, and [`ShellTask::descriptor`] returns the script itself.
+    ///
+    /// References to the temporary file in log lines are rewritten to point at the Rust source that called
+    /// [`ShellTask::script`], so errors like `line 3: foo: command not found` point at your code instead.
+    /// Line numbers are mapped assuming the script starts on the same line as the call.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use shell_candy::{ShellTask, ShellTaskBehavior, ShellTaskOutput};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    ///     let task = ShellTask::script("sh", r#"
+    ///         greeting="it's quoted"
+    ///         echo "$greeting"
+    ///     "#)?;
+    ///     let ShellTaskOutput::CompleteOutput { stdout_lines, .. } =
+    ///         task.run(|_| ShellTaskBehavior::<()>::Passthrough)?
+    ///     else {
+    ///         unreachable!("the log handler never returns early")
+    ///     };
+    ///     assert_eq!(stdout_lines, vec!["it's quoted".to_string()]);
+    ///     Ok(())
+    /// }
+    /// ```
+    #[track_caller]
+    pub fn script(interpreter: &str, contents: &str) -> Result<Self> {
+        let origin = Location::caller();
+        let mut args: Vec<String> = interpreter.split(' ').map(|s| s.to_string()).collect();
+        if args[0].is_empty() {
+            return Err(Error::InvalidTask {
+                task: contents.to_string(),
+                reason: "an empty string is not an interpreter".to_string(),
+            });
         }
+        let bin = args.remove(0);
+        let script = ScriptFile::create(interpreter, contents, origin)?;
+        args.push(script.path().display().to_string());
+        let mut task = Self::from_parts(bin, args, contents.to_string())?;
+        task.script = Some(Arc::new(script));
+        Ok(task)
+    }
+
+    fn from_parts(bin: String, args: Vec<String>, full_command: String) -> Result<Self> {
+        let current_dir =
+            env::current_dir().map_err(|source| Error::CouldNotFindCurrentDirectory { source })?;
+        Ok(Self {
+            bin,
+            args,
+            full_command,
+            failure_output_tail: None,
+            success_criteria: SuccessCriteria::default(),
+            env: TaskEnv::default(),
+            current_dir,
+            shell: None,
+            script: None,
+            expand: false,
+            secrets: Vec::new(),
+            stdin: ShellTaskStdin::default(),
+            timeout: None,
+            collection: ShellTaskCollection::default(),
+            process_group: false,
+            die_with_parent: false,
+        })
     }
 
     /// Adds an environment variable to the command run by [`ShellTask`].
@@ -73,22 +270,395 @@ impl ShellTask {
         K: AsRef<OsStr>,
         V: AsRef<OsStr>,
     {
-        self.envs
-            .insert(key.as_ref().to_os_string(), value.as_ref().to_os_string());
+        self.env.set(key.as_ref(), value.as_ref());
+        self
+    }
+
+    /// Adds multiple environment variables to the command run by [`ShellTask`].
+    pub fn envs<I, K, V>(&mut self, vars: I) -> &mut ShellTask
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        for (key, value) in vars {
+            self.env.set(key.as_ref(), value.as_ref());
+        }
+        self
+    }
+
+    /// Removes an environment variable from the command run by [`ShellTask`],
+    /// whether it was inherited or explicitly set.
+    pub fn env_remove<K>(&mut self, key: K) -> &mut ShellTask
+    where
+        K: AsRef<OsStr>,
+    {
+        self.env.remove(key.as_ref());
+        self
+    }
+
+    /// Clears every environment variable, including inherited ones and
+    /// those previously added with [`ShellTask::env`].
+    pub fn env_clear(&mut self) -> &mut ShellTask {
+        self.env.clear();
+        self
+    }
+
+    /// Loads environment variables from a dotenv file and adds them to the command run by [`ShellTask`].
+    ///
+    /// Each line is a `KEY=VALUE` pair, optionally prefixed with `export`. Values can be
+    /// single-quoted to be taken literally, or double-quoted to support escapes like `\n`.
+    /// `${VAR}`, `${VAR:-default}`, and `$VAR` in unquoted and double-quoted values are replaced with variables
+    /// defined earlier in the file or already set for this task. Lines starting with `#` are comments.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use shell_candy::{ShellTask, ShellTaskBehavior, ShellTaskOutput};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    ///     let path = std::env::temp_dir().join("shell-candy-env-file-example.env");
+    ///     std::fs::write(
+    ///         &path,
+    ///         "# greetings\nexport NAME='world'\nGREETING=\"hello ${NAME}\" # inline comment\n",
+    ///     )?;
+    ///     let mut task = ShellTask::new("printenv GREETING")?;
+    ///     task.env_file(&path)?;
+    ///     let ShellTaskOutput::CompleteOutput { stdout_lines, .. } =
+    ///         task.run(|_| ShellTaskBehavior::<()>::Passthrough)?
+    ///     else {
+    ///         unreachable!("the log handler never returns early")
+    ///     };
+    ///     assert_eq!(stdout_lines, vec!["hello world".to_string()]);
+    ///     std::fs::remove_file(&path)?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn env_file<P>(&mut self, path: P) -> Result<&mut ShellTask>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(|source| Error::CouldNotReadEnvFile {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let vars = dotenv::parse(&contents, |key| {
+            self.env
+                .get(OsStr::new(key))
+                .map(|value| value.to_string_lossy().to_string())
+        })
+        .map_err(|(line, reason)| Error::InvalidEnvFile {
+            path: path.to_path_buf(),
+            line,
+            reason,
+        })?;
+        Ok(self.envs(vars))
+    }
+
+    /// Only inherits the listed environment variables from the current process
+    /// instead of the entire environment. Variables added with [`ShellTask::env`] are still set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use shell_candy::{ShellTask, ShellTaskBehavior, ShellTaskOutput};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    ///     let mut task = ShellTask::new("env")?;
+    ///     task.env_inherit_only(["PATH", "HOME", "LANG"]).env("HERMETIC", "1");
+    ///     let ShellTaskOutput::CompleteOutput { stdout_lines, .. } =
+    ///         task.run(|_| ShellTaskBehavior::<()>::Passthrough)?
+    ///     else {
+    ///         unreachable!("the log handler never returns early")
+    ///     };
+    ///     assert!(stdout_lines.iter().all(|line| {
+    ///         ["PATH=", "HOME=", "LANG=", "HERMETIC=", "SHELL_CANDY="]
+    ///             .iter()
+    ///             .any(|key| line.starts_with(key))
+    ///     }));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn env_inherit_only<I, K>(&mut self, keys: I) -> &mut ShellTask
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<OsStr>,
+    {
+        self.env.inherit_only(
+            keys.into_iter()
+                .map(|k| k.as_ref().to_os_string())
+                .collect(),
+        );
+        self
+    }
+
+    /// Expands `$VAR`, `${VAR}`, `${VAR:-default}`, and a leading `~` in the binary and arguments
+    /// of the command before it runs, without invoking a shell. Variables are looked up in the environment
+    /// of the task, so anything set with [`ShellTask::env`] is available.
+    ///
+    /// Running the task fails with [`Error::InvalidTask`] if a variable without a default is not set.
+    /// This has no effect on tasks created with [`ShellTask::shell`] or [`ShellTask::script`],
+    /// since their shell or interpreter does its own expansion.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use shell_candy::{Error, ShellTask, ShellTaskBehavior, ShellTaskOutput};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    ///     let mut task = ShellTask::new("echo $GREETING ${NAME:-world}")?;
+    ///     task.expand().env("GREETING", "hello");
+    ///     let ShellTaskOutput::CompleteOutput { stdout_lines, .. } =
+    ///         task.run(|_| ShellTaskBehavior::<()>::Passthrough)?
+    ///     else {
+    ///         unreachable!("the log handler never returns early")
+    ///     };
+    ///     assert_eq!(stdout_lines, vec!["hello world".to_string()]);
+    ///
+    ///     task.env_remove("GREETING");
+    ///     let error = task.run(|_| ShellTaskBehavior::<()>::Passthrough).unwrap_err();
+    ///     assert!(matches!(error, Error::InvalidTask { .. }));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn expand(&mut self) -> &mut ShellTask {
+        self.expand = true;
         self
     }
 
     /// Sets the directory the command should be run in.
-    pub fn current_dir<P>(&mut self, path: P)
+    pub fn current_dir<P>(&mut self, path: P) -> &mut ShellTask
     where
         P: AsRef<Path>,
     {
         self.current_dir = path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Adds an argument to the command run by [`ShellTask`]. Unlike the command passed to
+    /// [`ShellTask::new`], the argument is never split on spaces.
+    pub fn arg<A>(&mut self, arg: A) -> &mut ShellTask
+    where
+        A: Into<String>,
+    {
+        let arg = arg.into();
+        self.full_command = format!("{} {}", self.full_command, arg);
+        self.args.push(arg);
+        self
+    }
+
+    /// Adds multiple arguments to the command run by [`ShellTask`], like [`ShellTask::arg`].
+    pub fn args<I, A>(&mut self, args: I) -> &mut ShellTask
+    where
+        I: IntoIterator<Item = A>,
+        A: Into<String>,
+    {
+        for arg in args {
+            self.arg(arg);
+        }
+        self
     }
 
-    /// Returns the full command that was used to instantiate this [`ShellTask`].
+    /// Sets what the command reads from `stdin`. By default, `stdin` is inherited from the current process.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use shell_candy::{ShellTask, ShellTaskBehavior, ShellTaskOutput};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    ///     let mut task = ShellTask::new("tr a-z A-Z")?;
+    ///     task.stdin("shout\n");
+    ///     let ShellTaskOutput::CompleteOutput { stdout_lines, .. } =
+    ///         task.run(|_| ShellTaskBehavior::<()>::Passthrough)?
+    ///     else {
+    ///         unreachable!("the log handler never returns early")
+    ///     };
+    ///     assert_eq!(stdout_lines, vec!["SHOUT".to_string()]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn stdin<S>(&mut self, stdin: S) -> &mut ShellTask
+    where
+        S: Into<ShellTaskStdin>,
+    {
+        self.stdin = stdin.into();
+        self
+    }
+
+    /// Kills the command if it is still running after `timeout`, making [`ShellTask::run`]
+    /// return [`Error::TimedOut`]. For [`ShellTask::start_service`], this limits how long the service may take to become ready.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut ShellTask {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Runs the command in its own process group, so that when it is killed, on a timeout, an early return,
+    /// a cancellation, or when its handle is dropped, every process it started is killed along with it.
+    /// Only has an effect on Unix.
+    ///
+    /// This is off by default because a process group of its own takes the command out of the terminal's
+    /// foreground: it no longer receives Ctrl-C from the terminal, and it is stopped if it reads from an
+    /// inherited `stdin`. Combine it with [`ShellTask::die_with_parent`] so that it does not outlive a
+    /// program that is interrupted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use shell_candy::{Error, ShellTask, ShellTaskBehavior, ShellTaskStdin};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    ///     let mut task = ShellTask::shell("sleep 30 & echo started; wait")?;
+    ///     task.process_group(true)
+    ///         .stdin(ShellTaskStdin::Null)
+    ///         .timeout(Duration::from_millis(500));
+    ///     // the background `sleep` is killed along with the shell instead of keeping the task alive
+    ///     let error = task.run(|_| ShellTaskBehavior::<()>::Passthrough).unwrap_err();
+    ///     assert!(matches!(error, Error::TimedOut { .. }));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn process_group(&mut self, enabled: bool) -> &mut ShellTask {
+        self.process_group = enabled;
+        self
+    }
+
+    /// Asks the kernel to kill the command with `SIGKILL` as soon as the thread that started it exits,
+    /// including when this program is killed or interrupted without running any destructors. Only has an effect on Linux.
+    ///
+    /// The signal is tied to the thread that spawns the command, not to the whole program, so a
+    /// [`ShellTaskService`] started from a thread that then exits is killed too.
+    pub fn die_with_parent(&mut self, enabled: bool) -> &mut ShellTask {
+        self.die_with_parent = enabled;
+        self
+    }
+
+    /// Sets which lines are kept in the [`ShellTaskOutput`] of the command. By default, every line is kept.
+    pub fn collect(&mut self, collection: ShellTaskCollection) -> &mut ShellTask {
+        self.collection = collection;
+        self
+    }
+
+    /// Limits the output attached to [`Error::TaskFailure`] to the last `lines` lines
+    /// of each stream. By default, every collected line is attached.
+    pub fn failure_output_tail(&mut self, lines: usize) -> &mut ShellTask {
+        self.failure_output_tail = Some(lines);
+        self
+    }
+
+    /// Treats any of the given exit codes as a successful run instead of only 0,
+    /// for tools like `grep` or `diff` that use non-zero codes for non-error outcomes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use shell_candy::{ShellTask, ShellTaskBehavior, ShellTaskOutput};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    ///     let mut task = ShellTask::new("grep needle Cargo.toml")?;
+    ///     task.success_codes([0, 1]);
+    ///     let result = task.run(|_| ShellTaskBehavior::<()>::Passthrough)?;
+    ///     assert!(matches!(
+    ///         result,
+    ///         ShellTaskOutput::CompleteOutput { status, .. } if status.code() == Some(1)
+    ///     ));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn success_codes<I>(&mut self, codes: I) -> &mut ShellTask
+    where
+        I: IntoIterator<Item = i32>,
+    {
+        self.success_criteria = SuccessCriteria::Codes(codes.into_iter().collect());
+        self
+    }
+
+    /// Decides whether a run was successful with a predicate that receives the exit status
+    /// and the lines printed to `stdout` and `stderr`, replacing any [`ShellTask::success_codes`].
+    pub fn success_when<F>(&mut self, predicate: F) -> &mut ShellTask
+    where
+        F: Fn(&ExitStatus, &[String], &[String]) -> bool + Send + Sync + 'static,
+    {
+        self.success_criteria = SuccessCriteria::Predicate(Arc::new(predicate));
+        self
+    }
+
+    /// Adds an argument to the command run by [`ShellTask`] that is replaced with `****`
+    /// everywhere this crate reports it, like [`ShellTask::redact`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use shell_candy::ShellTask;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    ///     let mut task = ShellTask::new("curl --oauth2-bearer")?;
+    ///     task.secret_arg("abc123");
+    ///     assert_eq!(task.descriptor(), "curl --oauth2-bearer ****");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn secret_arg<A>(&mut self, arg: A) -> &mut ShellTask
+    where
+        A: Into<String>,
+    {
+        let arg = arg.into();
+        self.full_command = format!("{} {}", self.full_command, arg);
+        self.args.push(arg.to_string());
+        self.redact(arg)
+    }
+
+    /// Adds an environment variable to the command run by [`ShellTask`] whose value is replaced
+    /// with `****` everywhere this crate reports it, like [`ShellTask::redact`].
+    pub fn secret_env<K, V>(&mut self, key: K, value: V) -> &mut ShellTask
+    where
+        K: AsRef<OsStr>,
+        V: Into<String>,
+    {
+        let value = value.into();
+        self.env.set(key.as_ref(), OsStr::new(&value));
+        self.redact(value)
+    }
+
+    /// Replaces `secret` with `****` in [`ShellTask::descriptor`], [`ShellTask::bash_descriptor`],
+    /// every [`ShellTaskLog`] passed to the log handler, the collected output, and every [`Error`] reported by the task.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use shell_candy::{ShellTask, ShellTaskBehavior, ShellTaskLog};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    ///     let mut task = ShellTask::new("echo --token abc123")?;
+    ///     task.redact("abc123");
+    ///     assert_eq!(task.bash_descriptor(), "$ echo --token ****");
+    ///     task.run(|line| {
+    ///         assert_eq!(line, ShellTaskLog::Stdout("--token ****".to_string()));
+    ///         ShellTaskBehavior::<()>::Passthrough
+    ///     })?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn redact<S>(&mut self, secret: S) -> &mut ShellTask
+    where
+        S: Into<String>,
+    {
+        let secret = secret.into();
+        if !secret.is_empty() && !self.secrets.contains(&secret) {
+            self.secrets.push(secret);
+            // replace longer secrets first so one that contains another is fully redacted
+            self.secrets
+                .sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+        }
+        self
+    }
+
+    /// Returns the full command that was used to instantiate this [`ShellTask`],
+    /// with any secrets replaced by `****`.
     pub fn descriptor(&self) -> String {
-        self.full_command.to_string()
+        self.redact_secrets(&self.full_command)
     }
 
     /// Returns the [`ShellTask::descriptor`] with the classic `$` shell prefix.
@@ -96,21 +666,227 @@ impl ShellTask {
         format!("$ {}", self.descriptor())
     }
 
+    /// Returns the absolute path of the binary this [`ShellTask`] runs, resolved against
+    /// the `PATH` of the task's environment and its current directory.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use shell_candy::ShellTask;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    ///     let mut task = ShellTask::new("rustc --version")?;
+    ///     assert!(task.resolved_bin()?.is_absolute());
+    ///
+    ///     task.env("PATH", "");
+    ///     assert!(task.resolved_bin().is_err());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn resolved_bin(&self) -> Result<PathBuf> {
+        resolve::resolve_bin(
+            &self.descriptor(),
+            &self.expand_word(&self.bin)?,
+            self.env.get(OsStr::new("PATH")),
+            &self.current_dir,
+        )
+    }
+
+    /// Registers a hint that is included in [`Error::BinaryNotFound`] whenever `bin` cannot be found,
+    /// such as telling users where to install it from. Hints apply to every [`ShellTask`] in the process.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use shell_candy::{Error, ShellTask, ShellTaskBehavior};
+    ///
+    /// ShellTask::register_install_hint("definitely-not-npm", "install Node.js from https://nodejs.org");
+    /// let error = ShellTask::new("definitely-not-npm install")
+    ///     .unwrap()
+    ///     .run(|_| ShellTaskBehavior::<()>::Passthrough)
+    ///     .unwrap_err();
+    /// assert!(matches!(error, Error::BinaryNotFound { install_hint: Some(_), .. }));
+    /// assert!(error.to_string().contains("https://nodejs.org"));
+    /// ```
+    pub fn register_install_hint<B, H>(bin: B, hint: H)
+    where
+        B: Into<String>,
+        H: Into<String>,
+    {
+        resolve::register_install_hint(bin.into(), hint.into())
+    }
+
     /// Returns the [`ShellTaskRunner`] from the internal configuration.
-    fn get_command(&self) -> Command {
-        let mut command = Command::new(&self.bin);
-        command
-            .args(&self.args)
-            .envs(&self.envs)
-            .current_dir(&self.current_dir);
-        command
+    fn get_command(&self) -> Result<Command> {
+        // checked up front, since spawning in a missing directory fails with the same error as a missing binary
+        if !self.current_dir.is_dir() {
+            return Err(Error::InvalidTask {
+                task: self.descriptor(),
+                reason: format!("'{}' is not a directory", self.current_dir.display()),
+            });
+        }
+        let mut command = Command::new(self.resolved_bin()?);
+        for arg in &self.args {
+            command.arg(self.expand_word(arg)?);
+        }
+        command.current_dir(&self.current_dir);
+        self.env.apply(&mut command);
+        process::configure(&mut command, self.process_group, self.die_with_parent);
+        Ok(command)
+    }
+
+    /// Checks that the task can be run as configured, without running it.
+    fn validate(&self) -> Result<()> {
+        if self.timeout == Some(Duration::ZERO) {
+            return Err(Error::InvalidTask {
+                task: self.descriptor(),
+                reason: "a timeout of zero would kill it immediately".to_string(),
+            });
+        }
+        self.get_command().map(|_| ())
+    }
+
+    /// Applies [`ShellTask::expand`] to a word of the command, if it was enabled.
+    fn expand_word(&self, word: &str) -> Result<String> {
+        if !self.expand || self.shell.is_some() || self.script.is_some() {
+            return Ok(word.to_string());
+        }
+        let lookup = |key: &str| {
+            self.env
+                .get(OsStr::new(key))
+                .map(|value| value.to_string_lossy().to_string())
+        };
+        let home = lookup("HOME")
+            .or_else(|| env::var_os("HOME").map(|home| home.to_string_lossy().to_string()));
+        expand::expand(word, lookup, home).map_err(|reason| Error::InvalidTask {
+            task: self.descriptor(),
+            reason,
+        })
+    }
+
+    /// Creates the [`Error`] describing a task that did not exit successfully.
+    fn failure(
+        &self,
+        exit_status: ExitStatus,
+        stdout_lines: Vec<String>,
+        stderr_lines: Vec<String>,
+    ) -> Error {
+        let task = self.descriptor();
+        let stdout_lines = self.failure_output(stdout_lines);
+        let stderr_lines = self.failure_output(stderr_lines);
+
+        #[cfg(unix)]
+        if let Some(signal) = exit_status.signal() {
+            return Error::TaskKilledBySignal {
+                task,
+                signal,
+                core_dumped: exit_status.core_dumped(),
+                stdout_lines,
+                stderr_lines,
+            };
+        }
+
+        if let Some(shell) = &self.shell {
+            if let Some(reason) = shell_failure_reason(shell, exit_status, &stderr_lines) {
+                return Error::ShellFailure {
+                    task,
+                    shell: shell.to_string(),
+                    reason: reason.to_string(),
+                    exit_status,
+                    stdout_lines,
+                    stderr_lines,
+                };
+            }
+        }
+
+        Error::TaskFailure {
+            task,
+            exit_status,
+            stdout_lines,
+            stderr_lines,
+        }
+    }
+
+    /// Calls `run`, reporting a panic as [`Error::TaskPanicked`] so that tasks running on other threads
+    /// always produce a result. The child is killed when its runner is dropped during unwinding.
+    pub(crate) fn catch_panic<F, T>(&self, run: F) -> Result<T>
+    where
+        F: FnOnce() -> Result<T>,
+    {
+        panic::catch_unwind(AssertUnwindSafe(run)).unwrap_or_else(|payload| {
+            Err(Error::TaskPanicked {
+                task: self.descriptor(),
+                message: self.redact_secrets(&panic_message(payload)),
+            })
+        })
+    }
+
+    /// Calls `log_handler` with `line`, reporting a panic as [`Error::HandlerPanicked`].
+    fn call_handler<F, L, T>(&self, log_handler: &F, line: L) -> Result<ShellTaskBehavior<T>>
+    where
+        F: Fn(L) -> ShellTaskBehavior<T>,
+    {
+        panic::catch_unwind(AssertUnwindSafe(|| (log_handler)(line))).map_err(|payload| {
+            Error::HandlerPanicked {
+                task: self.descriptor(),
+                message: self.redact_secrets(&panic_message(payload)),
+            }
+        })
+    }
+
+    /// Creates the [`Error`] describing a task that was killed because of [`ShellTask::timeout`].
+    fn timed_out(&self, stdout_lines: CollectedLines, stderr_lines: CollectedLines) -> Error {
+        Error::TimedOut {
+            task: self.descriptor(),
+            timeout: self.timeout.unwrap_or_default(),
+            stdout_lines: self.failure_output(stdout_lines.into_vec()),
+            stderr_lines: self.failure_output(stderr_lines.into_vec()),
+        }
+    }
+
+    pub(crate) fn cancelled(&self) -> Error {
+        Error::Cancelled {
+            task: self.descriptor(),
+        }
+    }
+
+    /// Rewrites a log line before it is collected and passed to the log handler.
+    fn map_log(&self, line: ShellTaskLog) -> ShellTaskLog {
+        let map_line = |line: String| {
+            let line = match &self.script {
+                Some(script) => script.map_line(line),
+                None => line,
+            };
+            self.redact_secrets(&line)
+        };
+        match line {
+            ShellTaskLog::Stdout(line) => ShellTaskLog::Stdout(map_line(line)),
+            ShellTaskLog::Stderr(line) => ShellTaskLog::Stderr(map_line(line)),
+        }
+    }
+
+    /// Replaces every secret registered with [`ShellTask::redact`] in `text` with `****`.
+    fn redact_secrets(&self, text: &str) -> String {
+        self.secrets.iter().fold(text.to_string(), |text, secret| {
+            text.replace(secret, "****")
+        })
+    }
+
+    /// Trims collected lines down to the configured [`ShellTask::failure_output_tail`].
+    fn failure_output(&self, mut lines: Vec<String>) -> Vec<String> {
+        if let Some(tail) = self.failure_output_tail {
+            lines.drain(..lines.len().saturating_sub(tail));
+        }
+        lines
     }
 
     /// Run a [`ShellTask`], applying the log handler to each line.
     ///
     /// You can make the task terminate early if your `log_handler`
     /// returns [`ShellTaskBehavior::EarlyReturn<T>`]. When this variant
-    /// is returned from a log handler, [`ShellTask::run`] will return [`Some<T>`].
+    /// is returned from a log handler, the task is killed and [`ShellTask::run`]
+    /// will return [`ShellTaskOutput::EarlyReturn`] for `Ok(T)` or [`Error::EarlyReturn`] for `Err(e)`,
+    /// regardless of the exit status of the killed task.
     ///
     /// # Example
     ///
@@ -157,128 +933,163 @@ impl ShellTask {
     ///     Ok(())
     /// }
     /// ```
+    ///
+    /// If your `log_handler` panics, the task is killed and [`ShellTask::run`]
+    /// returns [`Error::HandlerPanicked`] instead of propagating the panic.
     pub fn run<F, T>(&self, log_handler: F) -> Result<ShellTaskOutput<T>>
     where
         F: Fn(ShellTaskLog) -> ShellTaskBehavior<T> + Send + Sync + 'static,
         T: Send + Sync + 'static,
     {
-        let log_drain: Arc<Mutex<Vec<ShellTaskLog>>> = Arc::new(Mutex::new(Vec::new()));
-        let log_drainer = log_drain.clone();
-        let log_drain_filler = log_drain.clone();
-        let log_receiver = self.log_receiver.clone();
-        let full_command = self.full_command.to_string();
-
-        let maybe_result = Arc::new(Mutex::new(None));
-        let early_terminator = maybe_result.clone();
-
-        let collected_stdout_lines = Arc::new(Mutex::new(Vec::new()));
-        let collected_stderr_lines = Arc::new(Mutex::new(Vec::new()));
-        let stdout_collector = collected_stdout_lines.clone();
-        let stderr_collector = collected_stderr_lines.clone();
-
-        rayon::spawn(move || {
-            while let Ok(line) = log_receiver.recv() {
-                match &line {
-                    ShellTaskLog::Stderr(stderr) => {
-                        if let Ok(mut stderr_lines) = stderr_collector.clone().lock() {
-                            stderr_lines.push(stderr.to_string())
-                        }
-                    }
-                    ShellTaskLog::Stdout(stdout) => {
-                        if let Ok(mut stdout_lines) = stdout_collector.clone().lock() {
-                            stdout_lines.push(stdout.to_string())
-                        }
-                    }
-                }
+        self.run_cancellable(log_handler, None)
+    }
 
-                if let Ok(mut log_decrementer) = log_drainer.clone().lock() {
-                    if let Some(stderr_pos) = log_decrementer
-                        .iter()
-                        .position(|e| matches!(e, ShellTaskLog::Stderr(_)))
-                    {
-                        log_decrementer.remove(stderr_pos);
-                    } else if let Some(stdout_pos) = log_decrementer
-                        .iter()
-                        .position(|e| matches!(e, ShellTaskLog::Stdout(_)))
-                    {
-                        log_decrementer.remove(stdout_pos);
-                    }
-                    match (log_handler)(line) {
-                        ShellTaskBehavior::EarlyReturn(early_return) => {
-                            if let Ok(mut maybe_result) = early_terminator.lock() {
-                                if maybe_result.is_none() {
-                                    *maybe_result = Some(early_return);
-                                    break;
-                                }
-                            }
-                        }
-                        ShellTaskBehavior::Passthrough => continue,
-                    }
-                } else if let Ok(mut maybe_result) = early_terminator.lock() {
-                    if maybe_result.is_none() {
-                        *maybe_result =
-                            Some(Err(Box::new(Error::PoisonedLog { task: full_command })));
-                        break;
-                    }
-                } else {
-                    continue;
+    /// Runs the task like [`ShellTask::run`], killing it and returning [`Error::Cancelled`]
+    /// as soon as `cancellation` is cancelled.
+    pub(crate) fn run_cancellable<F, T>(
+        &self,
+        log_handler: F,
+        cancellation: Option<&Cancellation>,
+    ) -> Result<ShellTaskOutput<T>>
+    where
+        F: Fn(ShellTaskLog) -> ShellTaskBehavior<T> + Send + Sync + 'static,
+        T: Send + Sync + 'static,
+    {
+        let (log_sender, log_receiver) = unbounded();
+        let mut task = ShellTaskRunner::run(
+            self.get_command()?,
+            self.descriptor(),
+            self.process_group,
+            log_sender,
+            &self.stdin,
+        )?;
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+
+        let mut maybe_result = None;
+        let mut collected_stdout_lines = CollectedLines::new(self.collection);
+        let mut collected_stderr_lines = CollectedLines::new(self.collection);
+
+        for event in LogStream::new(log_receiver, deadline, cancellation) {
+            let line = match event {
+                LogEvent::Line(_, line) => self.map_log(line),
+                LogEvent::Failed(e) => {
+                    task.kill();
+                    return Err(e);
+                }
+                LogEvent::TimedOut => {
+                    task.kill();
+                    return Err(self.timed_out(collected_stdout_lines, collected_stderr_lines));
+                }
+                LogEvent::Cancelled => {
+                    task.kill();
+                    return Err(self.cancelled());
                 }
+            };
+            match &line {
+                ShellTaskLog::Stderr(stderr) => collected_stderr_lines.push(stderr),
+                ShellTaskLog::Stdout(stdout) => collected_stdout_lines.push(stdout),
             }
-        });
 
-        let task = ShellTaskRunner::run(
-            self.get_command(),
-            self.full_command.to_string(),
-            self.log_sender.clone(),
-            log_drain_filler,
-        )?;
-
-        let output = task
-            .child
-            .wait_with_output()
-            .map_err(|source| Error::CouldNotWait {
-                task: self.full_command.to_string(),
-                source,
-            })?;
-
-        // wait until the log drain is empty so we know they've all been processed
-        loop {
-            std::thread::sleep(Duration::from_millis(200));
-            match log_drain.try_lock() {
-                Ok(log_drain) => {
-                    if log_drain.is_empty() {
-                        break;
-                    } else {
-                        continue;
-                    }
+            match self.call_handler(&log_handler, line) {
+                Ok(ShellTaskBehavior::EarlyReturn(early_return)) => {
+                    maybe_result = Some(early_return);
+                    break;
+                }
+                Ok(ShellTaskBehavior::Passthrough) => continue,
+                Err(e) => {
+                    task.kill();
+                    return Err(e);
                 }
-                _ => continue,
             }
         }
 
-        if output.status.success() {
-            let collected_stderr_lines = collected_stderr_lines.lock().unwrap().to_vec();
-            let collected_stdout_lines = collected_stdout_lines.lock().unwrap().to_vec();
-            if let Some(result) = maybe_result.clone().lock().unwrap().take() {
-                result
-                    .map(|t| ShellTaskOutput::EarlyReturn {
-                        stderr_lines: collected_stderr_lines,
-                        stdout_lines: collected_stdout_lines,
-                        return_value: t,
-                    })
-                    .map_err(|e| e.into())
-            } else {
-                Ok(ShellTaskOutput::CompleteOutput {
-                    status: output.status,
-                    stdout_lines: collected_stdout_lines,
+        if maybe_result.is_some() {
+            task.terminate();
+        }
+
+        let status =
+            task.wait_until(deadline, cancellation)
+                .map_err(|source| Error::CouldNotWait {
+                    task: self.descriptor(),
+                    source,
+                })?;
+        let status = match status {
+            Some(status) => status,
+            None if cancellation.is_some_and(Cancellation::is_cancelled) => {
+                return Err(self.cancelled())
+            }
+            None => return Err(self.timed_out(collected_stdout_lines, collected_stderr_lines)),
+        };
+        let collected_stdout_lines = collected_stdout_lines.into_vec();
+        let collected_stderr_lines = collected_stderr_lines.into_vec();
+
+        // an early return from the log handler always takes precedence over the exit status,
+        // since killing the task usually makes it exit unsuccessfully
+        if let Some(result) = maybe_result {
+            result
+                .map(|t| ShellTaskOutput::EarlyReturn {
+                    status,
                     stderr_lines: collected_stderr_lines,
+                    stdout_lines: collected_stdout_lines,
+                    return_value: t,
                 })
-            }
-        } else {
-            Err(Error::TaskFailure {
-                task: self.full_command.to_string(),
-                exit_status: output.status,
+                .map_err(|e| e.into())
+        } else if self.success_criteria.is_success(
+            &status,
+            &collected_stdout_lines,
+            &collected_stderr_lines,
+        ) {
+            Ok(ShellTaskOutput::CompleteOutput {
+                status,
+                stdout_lines: collected_stdout_lines,
+                stderr_lines: collected_stderr_lines,
             })
+        } else {
+            Err(self.failure(status, collected_stdout_lines, collected_stderr_lines))
         }
     }
 }
+
+/// Recognizes failures of the shell itself, rather than of the script it ran, from the diagnostics
+/// the shell prints with its own name as a prefix, like `sh: 1: foo: not found` or `/bin/bash: line 1: syntax error ...`.
+/// Output from the commands in the script, and a script that simply exits with 126 or 127, are not mistaken for them.
+fn shell_failure_reason(
+    shell: &str,
+    exit_status: ExitStatus,
+    stderr_lines: &[String],
+) -> Option<&'static str> {
+    // the shell is spawned by its resolved path, which it uses as its name, so only file names are compared
+    let shell_name = Path::new(shell.split(' ').next()?).file_name()?;
+    let diagnostics: Vec<&String> = stderr_lines
+        .iter()
+        .filter(|line| {
+            line.split_once(':')
+                .and_then(|(name, _)| Path::new(name).file_name())
+                .is_some_and(|name| name == shell_name)
+        })
+        .collect();
+    let reports = |needles: &[&str]| {
+        diagnostics
+            .iter()
+            .any(|line| needles.iter().any(|needle| line.contains(needle)))
+    };
+    match exit_status.code()? {
+        126 if reports(&["Permission denied", "cannot execute"]) => {
+            Some("a command in the script could not be executed")
+        }
+        127 if reports(&["not found"]) => Some("a command in the script could not be found"),
+        _ if reports(&["syntax error", "Syntax error"]) => Some("the script has a syntax error"),
+        _ => None,
+    }
+}
+
+/// Extracts the message from a panic payload caught while running a log handler.
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.to_string()
+    } else {
+        "a non-string payload".to_string()
+    }
+}
@@ -5,6 +5,10 @@ use std::process::ExitStatus;
 pub enum ShellTaskOutput<T> {
     /// This variant is returned when the specified log handler returns early or runs into an unrecoverable error.
     EarlyReturn {
+        /// The exit status of the task after it was terminated. This is usually unsuccessful
+        /// since the task is killed when the log handler returns early.
+        status: ExitStatus,
+
         /// The lines printed to `stdout` by the task up until the point of early return.
         stdout_lines: Vec<String>,
 
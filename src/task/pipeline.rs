@@ -0,0 +1,302 @@
+use std::{
+    process::Stdio,
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::unbounded;
+
+use crate::{
+    Error, Result, ShellPipelineLog, ShellTask, ShellTaskBehavior, ShellTaskLog, ShellTaskOutput,
+};
+
+use super::{
+    collection::CollectedLines,
+    runner::{LogEvent, LogStream, ShellTaskRunner, StageStdin},
+};
+
+/// A [`ShellPipeline`] connects the `stdout` of each [`ShellTask`] to the `stdin` of the next,
+/// like `a | b | c` in a shell, without invoking a shell.
+///
+/// The log handler receives `stderr` from every stage and `stdout` from the last stage,
+/// tagged with the index of the stage that printed them. Like `set -o pipefail`, the pipeline
+/// fails if any stage fails, and the error names the rightmost stage that failed.
+///
+/// # Example
+///
+/// ```
+/// use shell_candy::{ShellTask, ShellTaskBehavior, ShellTaskOutput};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+///     let pipeline = ShellTask::new("printf b\\na\\nc\\n")?
+///         .pipe(ShellTask::new("sort")?)
+///         .pipe(ShellTask::new("head -n 2")?);
+///     assert_eq!(pipeline.descriptor(), "printf b\\na\\nc\\n | sort | head -n 2");
+///     let ShellTaskOutput::CompleteOutput { stdout_lines, .. } =
+///         pipeline.run(|_| ShellTaskBehavior::<()>::Passthrough)?
+///     else {
+///         unreachable!("the log handler never returns early")
+///     };
+///     assert_eq!(stdout_lines, vec!["a".to_string(), "b".to_string()]);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ShellPipeline {
+    stages: Vec<ShellTask>,
+    timeout: Option<Duration>,
+}
+
+impl ShellPipeline {
+    /// Create a new [`ShellPipeline`] from its stages, in order.
+    /// Only the first stage uses its own [`ShellTask::stdin`].
+    pub fn new<I>(stages: I) -> Self
+    where
+        I: IntoIterator<Item = ShellTask>,
+    {
+        Self {
+            stages: stages.into_iter().collect(),
+            timeout: None,
+        }
+    }
+
+    /// Adds a stage to the end of the pipeline.
+    pub fn pipe(mut self, next: ShellTask) -> Self {
+        self.stages.push(next);
+        self
+    }
+
+    /// Kills every stage if the pipeline is still running after `timeout`, making
+    /// [`ShellPipeline::run`] return [`Error::TimedOut`]. Timeouts set on individual stages are ignored.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the descriptors of every stage joined with `|`.
+    pub fn descriptor(&self) -> String {
+        self.stages
+            .iter()
+            .map(|stage| stage.descriptor())
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+
+    /// Returns the [`ShellPipeline::descriptor`] with the classic `$` shell prefix.
+    pub fn bash_descriptor(&self) -> String {
+        format!("$ {}", self.descriptor())
+    }
+
+    /// Run a [`ShellPipeline`], applying the log handler to each line.
+    ///
+    /// This behaves like [`ShellTask::run`]: an early return from the log handler kills every stage
+    /// and takes precedence over their exit statuses, and a panic in the log handler is reported as
+    /// [`Error::HandlerPanicked`]. [`ShellTaskOutput::CompleteOutput`] contains the status of the last stage.
+    pub fn run<F, T>(&self, log_handler: F) -> Result<ShellTaskOutput<T>>
+    where
+        F: Fn(ShellPipelineLog) -> ShellTaskBehavior<T> + Send + Sync + 'static,
+        T: Send + Sync + 'static,
+    {
+        let last_stage = match self.stages.len() {
+            0 => {
+                return Err(Error::InvalidTask {
+                    task: self.descriptor(),
+                    reason: "a pipeline needs at least one task".to_string(),
+                })
+            }
+            len => len - 1,
+        };
+
+        let (log_sender, log_receiver) = unbounded();
+        let mut runners: Vec<ShellTaskRunner> = Vec::new();
+        for (stage, task) in self.stages.iter().enumerate() {
+            let stdin = match runners.last_mut() {
+                Some(previous) => match previous.child.stdout.take() {
+                    Some(stdout) => StageStdin::Stdio(Stdio::from(stdout)),
+                    None => StageStdin::Stdio(Stdio::null()),
+                },
+                None => (&task.stdin).into(),
+            };
+            let runner = task.get_command().and_then(|command| {
+                ShellTaskRunner::spawn(
+                    command,
+                    task.descriptor(),
+                    task.process_group,
+                    stage,
+                    stdin,
+                    stage == last_stage,
+                    log_sender.clone(),
+                )
+            });
+            match runner {
+                Ok(runner) => runners.push(runner),
+                Err(e) => {
+                    kill_all(&mut runners);
+                    return Err(e);
+                }
+            }
+        }
+        drop(log_sender);
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+
+        let mut maybe_result = None;
+        let collection = self.stages[last_stage].collection;
+        let mut collected_stdout_lines = CollectedLines::new(collection);
+        let mut collected_stderr_lines = CollectedLines::new(collection);
+        let mut stage_stderr_lines: Vec<CollectedLines> = self
+            .stages
+            .iter()
+            .map(|task| CollectedLines::new(task.collection))
+            .collect();
+
+        for event in LogStream::new(log_receiver, deadline, None) {
+            let (stage, line) = match event {
+                LogEvent::Line(stage, line) => (stage, self.stages[stage].map_log(line)),
+                LogEvent::Failed(e) => {
+                    kill_all(&mut runners);
+                    return Err(e);
+                }
+                LogEvent::TimedOut => {
+                    kill_all(&mut runners);
+                    return Err(self.timed_out(collected_stdout_lines, collected_stderr_lines));
+                }
+                LogEvent::Cancelled => unreachable!("pipelines are never cancelled"),
+            };
+            match &line {
+                ShellTaskLog::Stderr(stderr) => {
+                    collected_stderr_lines.push(stderr);
+                    stage_stderr_lines[stage].push(stderr);
+                }
+                ShellTaskLog::Stdout(stdout) => collected_stdout_lines.push(stdout),
+            }
+
+            let log = ShellPipelineLog { stage, log: line };
+            match self.stages[stage].call_handler(&log_handler, log) {
+                Ok(ShellTaskBehavior::EarlyReturn(early_return)) => {
+                    maybe_result = Some(early_return);
+                    break;
+                }
+                Ok(ShellTaskBehavior::Passthrough) => continue,
+                Err(Error::HandlerPanicked { message, .. }) => {
+                    kill_all(&mut runners);
+                    return Err(Error::HandlerPanicked {
+                        task: self.descriptor(),
+                        message: self.redact_secrets(&message),
+                    });
+                }
+                Err(e) => {
+                    kill_all(&mut runners);
+                    return Err(e);
+                }
+            }
+        }
+
+        if maybe_result.is_some() {
+            for runner in &mut runners {
+                runner.terminate();
+            }
+        }
+
+        let mut statuses = Vec::new();
+        for (task, runner) in self.stages.iter().zip(&mut runners) {
+            let status =
+                runner
+                    .wait_until(deadline, None)
+                    .map_err(|source| Error::CouldNotWait {
+                        task: task.descriptor(),
+                        source,
+                    })?;
+            match status {
+                Some(status) => statuses.push(status),
+                None => {
+                    kill_all(&mut runners);
+                    return Err(self.timed_out(collected_stdout_lines, collected_stderr_lines));
+                }
+            }
+        }
+        let status = statuses[last_stage];
+        let collected_stdout_lines = collected_stdout_lines.into_vec();
+        let collected_stderr_lines = collected_stderr_lines.into_vec();
+
+        // an early return from the log handler always takes precedence over the exit status,
+        // since killing the stages usually makes them exit unsuccessfully
+        if let Some(result) = maybe_result {
+            return result
+                .map(|t| ShellTaskOutput::EarlyReturn {
+                    status,
+                    stderr_lines: collected_stderr_lines,
+                    stdout_lines: collected_stdout_lines,
+                    return_value: t,
+                })
+                .map_err(|e| e.into());
+        }
+
+        // only the last stage has its stdout collected, since every other stage writes to the next one
+        let failed_stage = self
+            .stages
+            .iter()
+            .zip(statuses)
+            .zip(stage_stderr_lines)
+            .enumerate()
+            .rev()
+            .find_map(|(stage, ((task, status), stderr_lines))| {
+                let stdout_lines = if stage == last_stage {
+                    collected_stdout_lines.clone()
+                } else {
+                    Vec::new()
+                };
+                let stderr_lines = stderr_lines.into_vec();
+                if task
+                    .success_criteria
+                    .is_success(&status, &stdout_lines, &stderr_lines)
+                {
+                    None
+                } else {
+                    Some((stage, task.failure(status, stdout_lines, stderr_lines)))
+                }
+            });
+
+        match failed_stage {
+            Some((stage, failure)) => Err(Error::PipelineFailure {
+                pipeline: self.descriptor(),
+                stage,
+                source: Box::new(failure),
+            }),
+            None => Ok(ShellTaskOutput::CompleteOutput {
+                status,
+                stdout_lines: collected_stdout_lines,
+                stderr_lines: collected_stderr_lines,
+            }),
+        }
+    }
+
+    /// Replaces the secrets of every stage in `text` with `****`.
+    fn redact_secrets(&self, text: &str) -> String {
+        self.stages
+            .iter()
+            .fold(text.to_string(), |text, task| task.redact_secrets(&text))
+    }
+
+    /// Creates the [`Error`] describing a pipeline that was killed because of [`ShellPipeline::timeout`].
+    fn timed_out(&self, stdout_lines: CollectedLines, stderr_lines: CollectedLines) -> Error {
+        Error::TimedOut {
+            task: self.descriptor(),
+            timeout: self.timeout.unwrap_or_default(),
+            stdout_lines: stdout_lines.into_vec(),
+            stderr_lines: stderr_lines.into_vec(),
+        }
+    }
+}
+
+impl ShellTask {
+    /// Creates a [`ShellPipeline`] that connects the `stdout` of this task to the `stdin` of `next`.
+    pub fn pipe(self, next: ShellTask) -> ShellPipeline {
+        ShellPipeline::new([self, next])
+    }
+}
+
+/// Kills every stage that has been spawned so far.
+fn kill_all(runners: &mut [ShellTaskRunner]) {
+    for runner in runners {
+        runner.kill();
+    }
+}
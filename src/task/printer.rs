@@ -0,0 +1,161 @@
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+    sync::Arc,
+};
+
+use crate::{ShellTaskBehavior, ShellTaskGraphLog, ShellTaskGroupLog, ShellTaskLog};
+
+#[cfg(doc)]
+use crate::{ShellTask, ShellTaskGraph, ShellTaskGroup, ShellTaskId};
+
+/// The ANSI colors labels cycle through, in order.
+const LABEL_COLORS: [&str; 6] = ["36", "33", "32", "35", "34", "96"];
+
+/// The ANSI color for the text of lines printed to `stderr`.
+const STDERR_COLOR: &str = "31";
+
+/// A [`ShellTaskPrinter`] prints the logs of tasks that run at the same time, prefixing each line
+/// with a padded, color-coded label for the task that printed it, like `docker compose` does.
+///
+/// Lines from `stdout` are printed to `stdout` and lines from `stderr` are printed to `stderr`
+/// with their text colored red. Colors are only used when the stream is a terminal and the
+/// [`NO_COLOR`](https://no-color.org) environment variable is not set, unless [`ShellTaskPrinter::color`] overrides it.
+///
+/// # Example
+///
+/// ```
+/// use shell_candy::{ShellTask, ShellTaskGroup, ShellTaskLog, ShellTaskPrinter};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+///     let group = ShellTaskGroup::new([ShellTask::new("echo ready")?, ShellTask::new("echo listening")?]);
+///     let printer = ShellTaskPrinter::new(["api", "frontend"]).color(false);
+///     assert_eq!(
+///         printer.format(0, &ShellTaskLog::Stdout("ready".to_string())),
+///         "api      | ready"
+///     );
+///     let output = group.run(printer.group_handler::<()>());
+///     assert!(output.is_success());
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ShellTaskPrinter {
+    labels: Arc<Vec<String>>,
+    width: usize,
+    color: Option<bool>,
+}
+
+impl ShellTaskPrinter {
+    /// Create a new [`ShellTaskPrinter`] with one label per task, in the order the tasks were added.
+    /// Lines from tasks without a label are prefixed with their index instead.
+    pub fn new<I, S>(labels: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let labels: Vec<String> = labels.into_iter().map(Into::into).collect();
+        let width = labels
+            .iter()
+            .map(|label| label.chars().count())
+            .max()
+            .unwrap_or_default();
+        Self {
+            labels: Arc::new(labels),
+            width,
+            color: None,
+        }
+    }
+
+    /// Always (`true`) or never (`false`) use colors, instead of detecting whether to use them.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Formats `log` as it would be printed for the task at `index`.
+    ///
+    /// Colors are included according to [`ShellTaskPrinter::color`] and the stream the log would be printed to.
+    pub fn format(&self, index: usize, log: &ShellTaskLog) -> String {
+        let label = match self.labels.get(index) {
+            Some(label) => label.to_string(),
+            None => index.to_string(),
+        };
+        let label = format!("{:<width$}", label, width = self.width);
+        let (line, is_stderr) = match log {
+            ShellTaskLog::Stdout(line) => (line, false),
+            ShellTaskLog::Stderr(line) => (line, true),
+        };
+        if !self.use_color(is_stderr) {
+            return format!("{} | {}", label, line);
+        }
+        let label_color = LABEL_COLORS[index % LABEL_COLORS.len()];
+        if is_stderr {
+            format!(
+                "\x1b[{}m{} |\x1b[0m \x1b[{}m{}\x1b[0m",
+                label_color, label, STDERR_COLOR, line
+            )
+        } else {
+            format!("\x1b[{}m{} |\x1b[0m {}", label_color, label, line)
+        }
+    }
+
+    /// Prints `log` for the task at `index`. Errors writing to the terminal, such as a closed pipe, are ignored.
+    pub fn print(&self, index: usize, log: &ShellTaskLog) {
+        let line = self.format(index, log);
+        // locking the stream keeps lines from concurrent tasks from interleaving
+        let _ = match log {
+            ShellTaskLog::Stdout(_) => writeln!(io::stdout().lock(), "{}", line),
+            ShellTaskLog::Stderr(_) => writeln!(io::stderr().lock(), "{}", line),
+        };
+    }
+
+    /// Returns a log handler for [`ShellTask::run`] that prints every line for the task at `index`.
+    pub fn handler<T>(
+        &self,
+        index: usize,
+    ) -> impl Fn(ShellTaskLog) -> ShellTaskBehavior<T> + Send + Sync + 'static {
+        let printer = self.clone();
+        move |log| {
+            printer.print(index, &log);
+            ShellTaskBehavior::Passthrough
+        }
+    }
+
+    /// Returns a log handler for [`ShellTaskGroup::run`] that labels every line by the task that printed it.
+    pub fn group_handler<T>(
+        &self,
+    ) -> impl Fn(ShellTaskGroupLog) -> ShellTaskBehavior<T> + Send + Sync + 'static {
+        let printer = self.clone();
+        move |line| {
+            printer.print(line.task, &line.log);
+            ShellTaskBehavior::Passthrough
+        }
+    }
+
+    /// Returns a log handler for [`ShellTaskGraph::run`] that labels every line by the [`ShellTaskId::index`]
+    /// of the task that printed it.
+    pub fn graph_handler<T>(
+        &self,
+    ) -> impl Fn(ShellTaskGraphLog) -> ShellTaskBehavior<T> + Send + Sync + 'static {
+        let printer = self.clone();
+        move |line| {
+            printer.print(line.task.index(), &line.log);
+            ShellTaskBehavior::Passthrough
+        }
+    }
+
+    fn use_color(&self, is_stderr: bool) -> bool {
+        if let Some(color) = self.color {
+            return color;
+        }
+        if env::var_os("NO_COLOR").is_some_and(|no_color| !no_color.is_empty()) {
+            return false;
+        }
+        if is_stderr {
+            io::stderr().is_terminal()
+        } else {
+            io::stdout().is_terminal()
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! Platform-specific control over the processes a task spawns.
+
+use std::process::{Child, Command};
+
+/// Applies [`crate::ShellTask::process_group`] and [`crate::ShellTask::die_with_parent`] to `command`.
+pub(crate) fn configure(command: &mut Command, process_group: bool, die_with_parent: bool) {
+    #[cfg(unix)]
+    if process_group {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    #[cfg(not(unix))]
+    let _ = process_group;
+
+    #[cfg(target_os = "linux")]
+    if die_with_parent {
+        set_parent_death_signal(command);
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = (command, die_with_parent);
+}
+
+/// Makes the kernel send `SIGKILL` to the child when the thread that spawned it exits.
+///
+/// This is the only unsafe code in the crate: `prctl` has to run in the child between `fork` and `exec`,
+/// and [`std::os::unix::process::CommandExt::pre_exec`] is the only way to do that.
+#[cfg(target_os = "linux")]
+#[allow(unsafe_code)]
+fn set_parent_death_signal(command: &mut Command) {
+    use nix::{
+        errno::Errno,
+        sys::{prctl, signal::Signal},
+        unistd::{getpid, getppid},
+    };
+    use std::os::unix::process::CommandExt;
+
+    let parent = getpid();
+    // SAFETY: `prctl(PR_SET_PDEATHSIG)` and `getppid` are async-signal-safe, and the closure neither allocates
+    // nor touches any state shared with the parent.
+    unsafe {
+        command.pre_exec(move || {
+            prctl::set_pdeathsig(Signal::SIGKILL)?;
+            // the parent may have exited before the death signal was set, in which case it will never be sent
+            if getppid() != parent {
+                return Err(Errno::ESRCH.into());
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Sends `SIGKILL` to `child`, and to every process in its process group if it leads one.
+/// Errors are ignored, since the child may already have exited.
+pub(crate) fn kill(child: &mut Child, process_group: bool) {
+    #[cfg(unix)]
+    if process_group {
+        use nix::{
+            sys::signal::{killpg, Signal},
+            unistd::Pid,
+        };
+        // the child was spawned with `process_group(0)`, so its process group id is its process id
+        if let Ok(pgid) = i32::try_from(child.id()) {
+            let _ = killpg(Pid::from_raw(pgid), Signal::SIGKILL);
+        }
+    }
+    let _ = child.kill();
+}
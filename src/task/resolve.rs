@@ -0,0 +1,122 @@
+use std::{
+    collections::HashMap,
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+use crate::Error;
+
+/// The most suggestions reported by [`Error::BinaryNotFound`].
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Install hints registered with [`crate::ShellTask::register_install_hint`], keyed by binary name.
+static INSTALL_HINTS: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+
+pub(crate) fn register_install_hint(bin: String, hint: String) {
+    if let Ok(mut hints) = INSTALL_HINTS.get_or_init(Default::default).lock() {
+        hints.insert(bin, hint);
+    }
+}
+
+fn install_hint(bin: &str) -> Option<String> {
+    INSTALL_HINTS
+        .get()?
+        .lock()
+        .ok()?
+        .get(bin)
+        .map(|hint| hint.to_string())
+}
+
+/// Resolves `bin` to an absolute path using `path` (the value of `PATH` for the task) and `current_dir`.
+pub(crate) fn resolve_bin(
+    task: &str,
+    bin: &str,
+    path: Option<OsString>,
+    current_dir: &Path,
+) -> Result<PathBuf, Error> {
+    which::which_in(bin, path.as_ref(), current_dir).map_err(|_| {
+        // binaries like `./node_modules/.bin/tsc` are only looked up relative to the current directory
+        let (searched_paths, suggestions) = if Path::new(bin).components().count() > 1 {
+            (vec![current_dir.to_path_buf()], Vec::new())
+        } else {
+            let searched_paths: Vec<PathBuf> = path
+                .as_ref()
+                .map(|path| env::split_paths(path).collect())
+                .unwrap_or_default();
+            let suggestions = suggest(bin, &searched_paths);
+            (searched_paths, suggestions)
+        };
+        Error::BinaryNotFound {
+            task: task.to_string(),
+            bin: bin.to_string(),
+            searched_paths,
+            suggestions,
+            install_hint: install_hint(bin),
+        }
+    })
+}
+
+/// Finds executables in `dirs` with names similar to `bin`, closest first.
+fn suggest(bin: &str, dirs: &[PathBuf]) -> Vec<String> {
+    let max_distance = bin.chars().count().max(3) / 3;
+    let mut candidates: Vec<(usize, String)> = dirs
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| is_executable(&entry.path()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter_map(|name| {
+            let distance = edit_distance(bin, &name);
+            (distance <= max_distance).then_some((distance, name))
+        })
+        .collect();
+    candidates.sort();
+    candidates.dedup_by(|a, b| a.1 == b.1);
+    candidates
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path)
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// The optimal string alignment distance between `a` and `b`: the number of insertions,
+/// deletions, substitutions, and transpositions of adjacent characters that turn one into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    distances[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
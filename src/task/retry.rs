@@ -0,0 +1,268 @@
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    io, mem,
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    Error, Result, ShellTask, ShellTaskBehavior, ShellTaskLog, ShellTaskOutput, ShellTaskRetryEvent,
+};
+
+use super::collection::CollectedLines;
+
+/// A predicate that decides whether to retry after a failed attempt,
+/// given its error and the lines it printed to `stdout` and `stderr`.
+type RetryPredicate = dyn Fn(&Error, &[String], &[String]) -> bool + Send + Sync + 'static;
+
+/// How long [`ShellTask::retry`] waits between attempts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryBackoff {
+    /// Wait the same amount of time before every retry.
+    Fixed(Duration),
+
+    /// Wait `initial` before the first retry and double the wait before every retry after it, up to `max`.
+    Exponential {
+        /// The wait before the first retry.
+        initial: Duration,
+
+        /// The longest wait between attempts.
+        max: Duration,
+    },
+}
+
+impl RetryBackoff {
+    /// Returns the wait before `retry`, where the first retry is 1.
+    fn delay(&self, retry: u32) -> Duration {
+        match *self {
+            RetryBackoff::Fixed(delay) => delay,
+            RetryBackoff::Exponential { initial, max } => initial
+                .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+                .min(max),
+        }
+    }
+}
+
+/// [`RetryPolicy`] decides how many times and how often [`ShellTask::retry`] runs a task that fails.
+///
+/// By default, a task runs at most 3 times, waiting 1 second before the first retry and twice as long before each
+/// retry after it, up to 30 seconds, and every failure is retried except for configuration errors.
+#[derive(Clone)]
+pub struct RetryPolicy {
+    /// The maximum number of times to run the task, including the first attempt. 0 is treated as 1.
+    pub attempts: usize,
+
+    /// How long to wait between attempts.
+    pub backoff: RetryBackoff,
+
+    /// The most time added at random to each wait, so that tasks that failed together do not retry in lockstep.
+    pub jitter: Duration,
+
+    /// Decides whether a failed attempt should be retried. When [`None`], every failure is retried except for
+    /// configuration errors that would fail the same way again, like a missing binary or an invalid env file.
+    pub retry_if: Option<Arc<RetryPredicate>>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: RetryBackoff::Exponential {
+                initial: Duration::from_secs(1),
+                max: Duration::from_secs(30),
+            },
+            jitter: Duration::ZERO,
+            retry_if: None,
+        }
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("attempts", &self.attempts)
+            .field("backoff", &self.backoff)
+            .field("jitter", &self.jitter)
+            .field("retry_if", &self.retry_if.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
+
+impl RetryPolicy {
+    /// Only retries a failed attempt if `predicate` returns true for its error
+    /// and the lines it printed to `stdout` and `stderr`.
+    pub fn retry_if<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&Error, &[String], &[String]) -> bool + Send + Sync + 'static,
+    {
+        self.retry_if = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Returns the wait before `retry`, where the first retry is 1, including jitter.
+    fn delay(&self, retry: u32) -> Duration {
+        let delay = self.backoff.delay(retry);
+        let jitter = self.jitter.as_nanos().min(u64::MAX as u128) as u64;
+        if jitter == 0 {
+            return delay;
+        }
+        // RandomState is seeded randomly, which is plenty for spreading retries out without a dependency
+        let random = RandomState::new().build_hasher().finish();
+        delay.saturating_add(Duration::from_nanos(random % (jitter + 1)))
+    }
+}
+
+/// Runs a [`ShellTask`] again when it fails, according to a [`RetryPolicy`]. Created with [`ShellTask::retry`].
+#[derive(Debug)]
+pub struct ShellTaskRetry<'a> {
+    task: &'a ShellTask,
+    policy: RetryPolicy,
+}
+
+/// [`ShellTaskRetryOutput`] is returned by [`ShellTaskRetry::run`] when an attempt succeeds or returns early.
+#[derive(Debug)]
+pub struct ShellTaskRetryOutput<T> {
+    /// The number of times the task ran, including the attempt that succeeded.
+    pub attempts: usize,
+
+    /// The output of the attempt that succeeded.
+    pub output: ShellTaskOutput<T>,
+}
+
+impl ShellTask {
+    /// Prepares to run this [`ShellTask`] with [`ShellTaskRetry::run`], which runs it again
+    /// whenever it fails, according to `policy`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use shell_candy::{Error, RetryBackoff, RetryPolicy, ShellTask, ShellTaskBehavior, ShellTaskRetryEvent};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    ///     let policy = RetryPolicy {
+    ///         attempts: 3,
+    ///         backoff: RetryBackoff::Fixed(Duration::from_millis(10)),
+    ///         ..Default::default()
+    ///     }
+    ///     .retry_if(|_, _, stderr_lines| stderr_lines.iter().any(|line| line.contains("ECONNRESET")));
+    ///
+    ///     let task = ShellTask::shell("echo 'read ECONNRESET' >&2; exit 1")?;
+    ///     let error = task
+    ///         .retry(policy)
+    ///         .run(|event| {
+    ///             if let ShellTaskRetryEvent::AttemptStarted { attempt } = event {
+    ///                 eprintln!("attempt {}", attempt);
+    ///             }
+    ///             ShellTaskBehavior::<()>::Passthrough
+    ///         })
+    ///         .unwrap_err();
+    ///     assert!(matches!(error, Error::RetryFailure { attempts: 3, .. }));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn retry(&self, policy: RetryPolicy) -> ShellTaskRetry<'_> {
+        ShellTaskRetry { task: self, policy }
+    }
+}
+
+impl ShellTaskRetry<'_> {
+    /// Runs the task until an attempt succeeds, an attempt returns early, or the [`RetryPolicy`] gives up.
+    ///
+    /// The log handler sees [`ShellTaskRetryEvent::AttemptStarted`] before each attempt, and every line
+    /// the attempt prints. The behavior it returns for [`ShellTaskRetryEvent::AttemptStarted`] is ignored.
+    ///
+    /// When the last attempt fails, returns [`Error::RetryFailure`] with the number of attempts.
+    /// Errors from the log handler itself, [`Error::EarlyReturn`] and [`Error::HandlerPanicked`],
+    /// are never retried.
+    pub fn run<F, T>(&self, log_handler: F) -> Result<ShellTaskRetryOutput<T>>
+    where
+        F: Fn(ShellTaskRetryEvent) -> ShellTaskBehavior<T> + Send + Sync + 'static,
+        T: Send + Sync + 'static,
+    {
+        let log_handler = Arc::new(log_handler);
+        let max_attempts = self.policy.attempts.max(1);
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            self.task.call_handler(
+                &*log_handler,
+                ShellTaskRetryEvent::AttemptStarted { attempt },
+            )?;
+
+            // collect what the attempt prints so retry_if sees it even when the error does not include it
+            let collection = self.task.collection;
+            let collected = Arc::new(Mutex::new((
+                CollectedLines::new(collection),
+                CollectedLines::new(collection),
+            )));
+            let attempt_handler = log_handler.clone();
+            let attempt_collected = collected.clone();
+            let result = self.task.run(move |log| {
+                {
+                    let mut collected = attempt_collected
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner);
+                    match &log {
+                        ShellTaskLog::Stdout(line) => collected.0.push(line),
+                        ShellTaskLog::Stderr(line) => collected.1.push(line),
+                    }
+                }
+                attempt_handler(ShellTaskRetryEvent::Log { attempt, log })
+            });
+
+            let error = match result {
+                Ok(output) => {
+                    return Ok(ShellTaskRetryOutput {
+                        attempts: attempt,
+                        output,
+                    })
+                }
+                Err(e) => e,
+            };
+
+            let should_retry = attempt < max_attempts
+                && !matches!(error, Error::EarlyReturn(_) | Error::HandlerPanicked { .. })
+                && match &self.policy.retry_if {
+                    Some(retry_if) => {
+                        let (stdout_lines, stderr_lines) = mem::replace(
+                            &mut *collected.lock().unwrap_or_else(PoisonError::into_inner),
+                            (
+                                CollectedLines::new(collection),
+                                CollectedLines::new(collection),
+                            ),
+                        );
+                        retry_if(&error, &stdout_lines.into_vec(), &stderr_lines.into_vec())
+                    }
+                    None => !is_configuration_error(&error),
+                };
+            if !should_retry {
+                return Err(Error::RetryFailure {
+                    task: self.task.descriptor(),
+                    attempts: attempt,
+                    source: Box::new(error),
+                });
+            }
+            thread::sleep(self.policy.delay(attempt as u32));
+        }
+    }
+}
+
+/// Returns true if `error` means the task is misconfigured, so running it again would fail the same way.
+fn is_configuration_error(error: &Error) -> bool {
+    match error {
+        Error::BinaryNotFound { .. }
+        | Error::InvalidTask { .. }
+        | Error::CouldNotReadEnvFile { .. }
+        | Error::InvalidEnvFile { .. }
+        | Error::CouldNotFindCurrentDirectory { .. } => true,
+        Error::CouldNotSpawn { source, .. } => matches!(
+            source.kind(),
+            io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied
+        ),
+        _ => false,
+    }
+}
@@ -1,79 +1,330 @@
 use std::{
-    io::{BufRead, BufReader},
-    process::{Child, Command, Stdio},
-    sync::{Arc, Mutex},
+    io::{self, BufRead, BufReader, Read, Write},
+    process::{Child, Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant},
 };
 
-use crossbeam_channel::Sender;
+use crossbeam_channel::{at, never, select, Receiver, Sender};
 
-use crate::{task::ShellTaskLog, Error, Result};
+use crate::{task::ShellTaskLog, Error, Result, ShellTaskStdin, ShellTaskStream};
 
-/// Runs a [`ShellTask`] in the background, reporting all logs and errors
+use super::process;
+
+/// A log line tagged with the index of the pipeline stage that printed it.
+/// Tasks that are not part of a pipeline are always stage 0.
+pub(crate) type StageLog = (usize, ShellTaskLog);
+
+/// Runs a [`ShellTask`] in the background, reporting all logs and errors.
+/// The child is killed if the runner is dropped while it is still running.
 #[derive(Debug)]
 pub(crate) struct ShellTaskRunner {
     pub(crate) child: Child,
+    process_group: bool,
 }
 
 impl ShellTaskRunner {
     pub(crate) fn run(
         command: Command,
         command_string: String,
-        log_sender: Sender<ShellTaskLog>,
-        log_incrementer: Arc<Mutex<Vec<ShellTaskLog>>>,
+        process_group: bool,
+        log_sender: Sender<Result<StageLog>>,
+        stdin: &ShellTaskStdin,
+    ) -> Result<Self> {
+        Self::spawn(
+            command,
+            command_string,
+            process_group,
+            0,
+            stdin.into(),
+            true,
+            log_sender,
+        )
+    }
+
+    /// Spawns one stage of a pipeline. When `read_stdout` is false, `stdout` is left
+    /// in [`Child::stdout`] so it can be connected to the next stage.
+    pub(crate) fn spawn(
+        command: Command,
+        command_string: String,
+        process_group: bool,
+        stage: usize,
+        stdin: StageStdin,
+        read_stdout: bool,
+        log_sender: Sender<Result<StageLog>>,
     ) -> Result<Self> {
         let mut command = command;
         command.env("SHELL_CANDY", "true");
         command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        let stdin_bytes = match stdin {
+            StageStdin::Stdio(stdio) => {
+                command.stdin(stdio);
+                None
+            }
+            StageStdin::Bytes(bytes) => {
+                command.stdin(Stdio::piped());
+                Some(bytes)
+            }
+        };
 
         let mut child = command.spawn().map_err(|source| Error::CouldNotSpawn {
-            task: command_string,
+            task: command_string.to_string(),
             source,
         })?;
 
-        let stdout_incrementer = log_incrementer.clone();
-        let stderr_incrementer = log_incrementer;
-
-        if let Some(stdout) = child.stdout.take() {
-            let log_sender = log_sender.clone();
-            rayon::spawn(move || {
-                let stdout = BufReader::new(stdout);
-                stdout.lines().for_each(|line| {
-                    if let Ok(line) = line {
-                        let guard = stdout_incrementer.clone();
-
-                        match guard.lock() {
-                            Ok(mut guard) => guard.push(ShellTaskLog::Stdout(line.to_string())),
-                            Err(e) => panic!("{}", e),
-                        }
-
-                        log_sender
-                            .send(ShellTaskLog::Stdout(line))
-                            .expect("could not update stdout logs for command");
-                    }
+        if let (Some(mut child_stdin), Some(bytes)) = (child.stdin.take(), stdin_bytes) {
+            // the task may exit without reading all of its input, so write errors are ignored
+            thread::spawn(move || child_stdin.write_all(&bytes));
+        }
+
+        // each reader gets a dedicated thread because it blocks until its pipe closes
+        if read_stdout {
+            if let Some(stdout) = child.stdout.take() {
+                let log_sender = log_sender.clone();
+                let command_string = command_string.to_string();
+                thread::spawn(move || {
+                    forward_lines(
+                        stdout,
+                        ShellTaskStream::Stdout,
+                        stage,
+                        command_string,
+                        log_sender,
+                    )
                 });
-            });
+            }
         }
 
         if let Some(stderr) = child.stderr.take() {
-            rayon::spawn(move || {
-                let stderr = BufReader::new(stderr);
-                stderr.lines().for_each(|line| {
-                    if let Ok(line) = line {
-                        let guard = stderr_incrementer.clone();
-
-                        match guard.lock() {
-                            Ok(mut guard) => guard.push(ShellTaskLog::Stderr(line.to_string())),
-                            Err(e) => panic!("{}", e),
-                        }
-
-                        log_sender
-                            .send(ShellTaskLog::Stderr(line))
-                            .expect("could not update stderr logs for command");
-                    }
-                });
+            thread::spawn(move || {
+                forward_lines(
+                    stderr,
+                    ShellTaskStream::Stderr,
+                    stage,
+                    command_string,
+                    log_sender,
+                )
             });
         }
 
-        Ok(Self { child })
+        Ok(Self {
+            child,
+            process_group,
+        })
+    }
+
+    /// Waits for the child process to exit, killing it if it is still running at `deadline`
+    /// or if `cancellation` is cancelled. Returns [`None`] if the child was killed.
+    pub(crate) fn wait_until(
+        &mut self,
+        deadline: Option<Instant>,
+        cancellation: Option<&Cancellation>,
+    ) -> io::Result<Option<ExitStatus>> {
+        if deadline.is_none() && cancellation.is_none() {
+            return self.child.wait().map(Some);
+        }
+        loop {
+            if let Some(status) = self.child.try_wait()? {
+                return Ok(Some(status));
+            }
+            let timed_out = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            if timed_out || cancellation.is_some_and(Cancellation::is_cancelled) {
+                self.kill();
+                return Ok(None);
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Kills the child process, and its process group if it has one, without reaping it.
+    pub(crate) fn terminate(&mut self) {
+        process::kill(&mut self.child, self.process_group);
+    }
+
+    /// Kills the child process and reaps it, ignoring any errors since the task is being abandoned.
+    pub(crate) fn kill(&mut self) {
+        self.terminate();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for ShellTaskRunner {
+    fn drop(&mut self) {
+        // a child that was already reaped keeps reporting its status, so only a running child is killed
+        if let Ok(None) = self.child.try_wait() {
+            self.kill();
+        }
+    }
+}
+
+/// Where a spawned stage reads `stdin` from.
+#[derive(Debug)]
+pub(crate) enum StageStdin {
+    /// An inherited, null, or piped [`Stdio`].
+    Stdio(Stdio),
+
+    /// Bytes that are written to a pipe and then closed.
+    Bytes(Vec<u8>),
+}
+
+impl From<&ShellTaskStdin> for StageStdin {
+    fn from(stdin: &ShellTaskStdin) -> Self {
+        match stdin {
+            ShellTaskStdin::Inherit => StageStdin::Stdio(Stdio::inherit()),
+            ShellTaskStdin::Null => StageStdin::Stdio(Stdio::null()),
+            ShellTaskStdin::Bytes(bytes) => StageStdin::Bytes(bytes.clone()),
+        }
+    }
+}
+
+/// Creates a [`Cancellation`] and the [`CancellationHandle`] that cancels it.
+pub(crate) fn cancellation() -> (CancellationHandle, Cancellation) {
+    let (sender, receiver) = crossbeam_channel::bounded(0);
+    (
+        CancellationHandle {
+            sender: Some(sender),
+        },
+        Cancellation { receiver },
+    )
+}
+
+/// Cancels every clone of its [`Cancellation`] at once by disconnecting their channel.
+#[derive(Debug)]
+pub(crate) struct CancellationHandle {
+    sender: Option<Sender<()>>,
+}
+
+impl CancellationHandle {
+    pub(crate) fn cancel(&mut self) {
+        self.sender.take();
+    }
+}
+
+/// Lets a running task be killed from another thread, such as by a sibling in a [`crate::ShellTaskGroup`].
+#[derive(Debug, Clone)]
+pub(crate) struct Cancellation {
+    receiver: Receiver<()>,
+}
+
+impl Cancellation {
+    pub(crate) fn is_cancelled(&self) -> bool {
+        matches!(
+            self.receiver.try_recv(),
+            Err(crossbeam_channel::TryRecvError::Disconnected)
+        )
+    }
+}
+
+/// Something that happened while receiving logs from a [`LogStream`].
+#[derive(Debug)]
+pub(crate) enum LogEvent {
+    /// A stage printed a line.
+    Line(usize, ShellTaskLog),
+
+    /// A reader failed.
+    Failed(Error),
+
+    /// The deadline passed before every reader was done.
+    TimedOut,
+
+    /// The task was cancelled before every reader was done.
+    Cancelled,
+}
+
+/// Receives logs until every reader is done, the deadline passes, or the task is cancelled.
+#[derive(Debug)]
+pub(crate) struct LogStream {
+    log_receiver: Receiver<Result<StageLog>>,
+    deadline: Receiver<Instant>,
+    cancellation: Receiver<()>,
+    stopped: bool,
+}
+
+impl LogStream {
+    pub(crate) fn new(
+        log_receiver: Receiver<Result<StageLog>>,
+        deadline: Option<Instant>,
+        cancellation: Option<&Cancellation>,
+    ) -> Self {
+        Self {
+            log_receiver,
+            deadline: deadline.map(at).unwrap_or_else(never),
+            cancellation: cancellation
+                .map(|cancellation| cancellation.receiver.clone())
+                .unwrap_or_else(never),
+            stopped: false,
+        }
+    }
+}
+
+impl Iterator for LogStream {
+    type Item = LogEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stopped {
+            return None;
+        }
+        // the receiver disconnects once every reader hits EOF, so every line has been received once this returns None
+        let log = select! {
+            recv(self.log_receiver) -> log => log.ok()?,
+            recv(self.deadline) -> _ => {
+                self.stopped = true;
+                return Some(LogEvent::TimedOut);
+            }
+            recv(self.cancellation) -> _ => {
+                self.stopped = true;
+                return Some(LogEvent::Cancelled);
+            }
+        };
+        Some(match log {
+            Ok((stage, line)) => LogEvent::Line(stage, line),
+            Err(e) => LogEvent::Failed(e),
+        })
+    }
+}
+
+/// Sends each line read from `reader` to `log_sender` until the stream closes or cannot be read.
+///
+/// A failed send means [`ShellTask::run`] has already returned and dropped its receiver,
+/// so there is nobody left to report to and the reader simply stops.
+fn forward_lines<R>(
+    reader: R,
+    stream: ShellTaskStream,
+    stage: usize,
+    command_string: String,
+    log_sender: Sender<Result<StageLog>>,
+) where
+    R: Read,
+{
+    let mut reader = BufReader::new(reader);
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        // tools may print Latin-1 or binary output, so lines are decoded lossily instead of
+        // failing the task; only real I/O errors are reported
+        let log = match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) => break,
+            Ok(_) => {
+                if buffer.ends_with(b"\n") {
+                    buffer.pop();
+                    if buffer.ends_with(b"\r") {
+                        buffer.pop();
+                    }
+                }
+                let line = String::from_utf8_lossy(&buffer).into_owned();
+                Ok(match stream {
+                    ShellTaskStream::Stdout => (stage, ShellTaskLog::Stdout(line)),
+                    ShellTaskStream::Stderr => (stage, ShellTaskLog::Stderr(line)),
+                })
+            }
+            Err(source) => Err(Error::CouldNotReadOutput {
+                task: command_string.to_string(),
+                stream,
+                source,
+            }),
+        };
+        let is_err = log.is_err();
+        if log_sender.send(log).is_err() || is_err {
+            break;
+        }
     }
 }
@@ -0,0 +1,133 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    panic::Location,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Error, Result};
+
+/// Distinguishes script files created by the same process.
+static SCRIPT_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A script written to a private temporary file for [`crate::ShellTask::script`].
+/// The file is removed when this is dropped.
+#[derive(Debug)]
+pub(crate) struct ScriptFile {
+    path: PathBuf,
+
+    /// The Rust source file and line the script was defined on.
+    origin: (String, u32),
+}
+
+impl ScriptFile {
+    pub(crate) fn create(
+        interpreter: &str,
+        contents: &str,
+        origin: &'static Location<'static>,
+    ) -> Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        let path = std::env::temp_dir().join(format!(
+            "shell-candy-{}-{}-{}{}",
+            process::id(),
+            SCRIPT_COUNTER.fetch_add(1, Ordering::Relaxed),
+            nanos,
+            extension(interpreter)
+        ));
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options
+            .open(&path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .map_err(|source| Error::CouldNotWriteScript {
+                path: path.to_path_buf(),
+                source,
+            })?;
+
+        Ok(Self {
+            path,
+            origin: (origin.file().to_string(), origin.line()),
+        })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Rewrites references to the temporary script file in a log line to point at the Rust source
+    /// the script was defined in, mapping line numbers reported by the interpreter along the way.
+    ///
+    /// Line numbers are mapped assuming the script starts on the same line as the call to
+    /// [`crate::ShellTask::script`], so the original script line is always included too.
+    pub(crate) fn map_line(&self, line: String) -> String {
+        let path = self.path.display().to_string();
+        let (file, origin_line) = &self.origin;
+        let mut mapped = String::new();
+        let mut rest = line.as_str();
+        while let Some(position) = rest.find(&path) {
+            mapped.push_str(&rest[..position]);
+            rest = &rest[position + path.len()..];
+
+            // bash reports `path: line 3`, python reports `"path", line 3`, and node reports `path:3`
+            let line_number = [": line ", "\", line ", ":"].iter().find_map(|prefix| {
+                let after = rest.strip_prefix(prefix)?;
+                let digits = after
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(after.len());
+                let script_line: u32 = after[..digits].parse().ok()?;
+                Some((script_line, prefix.len() + digits))
+            });
+            match line_number {
+                Some((script_line, len)) => {
+                    let quote = if rest.starts_with('"') { "\"" } else { "" };
+                    mapped.push_str(&format!(
+                        "{}:{}{} (script line {})",
+                        file,
+                        origin_line + script_line.saturating_sub(1),
+                        quote,
+                        script_line
+                    ));
+                    rest = &rest[len..];
+                }
+                None => mapped.push_str(&format!("{}:{}", file, origin_line)),
+            }
+        }
+        mapped.push_str(rest);
+        mapped
+    }
+}
+
+impl Drop for ScriptFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Picks a file extension for the interpreter, since some interpreters (like `node`) care about it.
+fn extension(interpreter: &str) -> &'static str {
+    let bin = interpreter.split(' ').next().unwrap_or_default();
+    let bin = Path::new(bin)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    match bin {
+        "sh" | "bash" | "zsh" | "dash" => ".sh",
+        "node" | "deno" | "bun" => ".js",
+        "ruby" => ".rb",
+        "perl" => ".pl",
+        _ if bin.starts_with("python") => ".py",
+        _ => "",
+    }
+}
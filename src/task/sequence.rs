@@ -0,0 +1,239 @@
+use std::sync::Arc;
+
+use crate::{Error, Result, ShellTask, ShellTaskBehavior, ShellTaskOutput, ShellTaskSequenceEvent};
+
+/// [`ShellTaskCondition`] decides whether a step of a [`ShellTaskSequence`] runs,
+/// based on the outcome of the last step that ran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellTaskCondition {
+    /// The step always runs, like `;` in a shell.
+    Always,
+
+    /// The step only runs if the last step succeeded, like `&&` in a shell.
+    OnSuccess,
+
+    /// The step only runs if the last step failed, like `||` in a shell.
+    OnFailure,
+}
+
+/// A [`ShellTaskSequence`] runs tasks one after another, deciding whether to run each step
+/// with a [`ShellTaskCondition`], like chaining commands with `;`, `&&`, and `||` in a shell.
+///
+/// The first step always runs. Skipped steps do not change the outcome that later conditions see,
+/// so `a && b || c` runs `c` if either `a` or `b` fails.
+///
+/// # Example
+///
+/// ```
+/// use shell_candy::{ShellTask, ShellTaskBehavior, ShellTaskSequence, ShellTaskSequenceEvent, ShellTaskSequenceStep};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+///     let output = ShellTaskSequence::new()
+///         .then(ShellTask::new("false")?)
+///         .and(ShellTask::new("echo unreachable")?)
+///         .or(ShellTask::new("echo recovered")?)
+///         .run(|event| {
+///             if let ShellTaskSequenceEvent::StepStarted { descriptor, .. } = event {
+///                 eprintln!("$ {}", descriptor);
+///             }
+///             ShellTaskBehavior::<()>::Passthrough
+///         })?;
+///     assert!(matches!(output.steps[0], ShellTaskSequenceStep::Failed(_)));
+///     assert!(matches!(output.steps[1], ShellTaskSequenceStep::Skipped));
+///     assert!(matches!(output.steps[2], ShellTaskSequenceStep::Ran(_)));
+///     assert_eq!(output.stopped_at, None);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct ShellTaskSequence {
+    steps: Vec<(ShellTaskCondition, ShellTask)>,
+}
+
+/// The outcome of one step of a [`ShellTaskSequence`].
+#[derive(Debug)]
+pub enum ShellTaskSequenceStep {
+    /// The step ran and either completed successfully or returned early.
+    /// Any early return value is reported in [`ShellTaskSequenceOutput::return_value`].
+    Ran(ShellTaskOutput<()>),
+
+    /// The step ran and failed.
+    Failed(Error),
+
+    /// The step did not run because its [`ShellTaskCondition`] did not match the outcome of the last step.
+    Skipped,
+
+    /// The step did not run because the log handler returned early before it was reached.
+    NotReached,
+}
+
+/// [`ShellTaskSequenceOutput`] is returned by [`ShellTaskSequence::run`] and describes every step.
+#[derive(Debug)]
+pub struct ShellTaskSequenceOutput<T> {
+    /// The outcome of each step, in order.
+    pub steps: Vec<ShellTaskSequenceStep>,
+
+    /// The step that stopped the chain, if any: either the step during which the log handler
+    /// returned early, or the failed step that left the sequence in a failed state.
+    pub stopped_at: Option<usize>,
+
+    /// The value the log handler returned early with, if it returned early.
+    pub return_value: Option<T>,
+}
+
+impl ShellTaskSequence {
+    /// Create a new, empty [`ShellTaskSequence`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a step that runs if `condition` matches the outcome of the last step that ran.
+    pub fn step(mut self, condition: ShellTaskCondition, task: ShellTask) -> Self {
+        self.steps.push((condition, task));
+        self
+    }
+
+    /// Adds a step that always runs, like `;` in a shell.
+    pub fn then(self, task: ShellTask) -> Self {
+        self.step(ShellTaskCondition::Always, task)
+    }
+
+    /// Adds a step that only runs if the last step succeeded, like `&&` in a shell.
+    pub fn and(self, task: ShellTask) -> Self {
+        self.step(ShellTaskCondition::OnSuccess, task)
+    }
+
+    /// Adds a step that only runs if the last step failed, like `||` in a shell.
+    pub fn or(self, task: ShellTask) -> Self {
+        self.step(ShellTaskCondition::OnFailure, task)
+    }
+
+    /// Returns the descriptors of every step joined with the shell operator matching its condition.
+    pub fn descriptor(&self) -> String {
+        let mut descriptor = String::new();
+        for (index, (condition, task)) in self.steps.iter().enumerate() {
+            if index > 0 {
+                descriptor.push_str(match condition {
+                    ShellTaskCondition::Always => "; ",
+                    ShellTaskCondition::OnSuccess => " && ",
+                    ShellTaskCondition::OnFailure => " || ",
+                });
+            }
+            descriptor.push_str(&task.descriptor());
+        }
+        descriptor
+    }
+
+    /// Returns the [`ShellTaskSequence::descriptor`] with the classic `$` shell prefix.
+    pub fn bash_descriptor(&self) -> String {
+        format!("$ {}", self.descriptor())
+    }
+
+    /// Run a [`ShellTaskSequence`], applying the log handler to every event.
+    ///
+    /// The log handler sees [`ShellTaskSequenceEvent::StepStarted`] before each step runs,
+    /// every line the step prints, and [`ShellTaskSequenceEvent::StepFinished`] after it exits.
+    /// Returning [`ShellTaskBehavior::EarlyReturn`] for any event stops the chain: `Ok(T)` is reported in
+    /// [`ShellTaskSequenceOutput::return_value`], and `Err(e)` is returned as [`Error::EarlyReturn`].
+    pub fn run<F, T>(&self, log_handler: F) -> Result<ShellTaskSequenceOutput<T>>
+    where
+        F: Fn(ShellTaskSequenceEvent) -> ShellTaskBehavior<T> + Send + Sync + 'static,
+        T: Send + Sync + 'static,
+    {
+        let log_handler = Arc::new(log_handler);
+        let mut steps = Vec::new();
+        let mut last_succeeded = true;
+        let mut last_ran = None;
+        let mut stopped_at = None;
+        let mut return_value = None;
+
+        for (step, (condition, task)) in self.steps.iter().enumerate() {
+            if stopped_at.is_some() {
+                steps.push(ShellTaskSequenceStep::NotReached);
+                continue;
+            }
+            let should_run = step == 0
+                || match condition {
+                    ShellTaskCondition::Always => true,
+                    ShellTaskCondition::OnSuccess => last_succeeded,
+                    ShellTaskCondition::OnFailure => !last_succeeded,
+                };
+            if !should_run {
+                steps.push(ShellTaskSequenceStep::Skipped);
+                continue;
+            }
+
+            let started = ShellTaskSequenceEvent::StepStarted {
+                step,
+                descriptor: task.descriptor(),
+            };
+            if let ShellTaskBehavior::EarlyReturn(result) =
+                task.call_handler(&*log_handler, started)?
+            {
+                return_value = Some(result?);
+                stopped_at = Some(step);
+                steps.push(ShellTaskSequenceStep::NotReached);
+                continue;
+            }
+
+            let step_handler = log_handler.clone();
+            let result =
+                task.run(move |log| step_handler(ShellTaskSequenceEvent::Log { step, log }));
+            last_ran = Some(step);
+            let outcome = match result {
+                Ok(ShellTaskOutput::EarlyReturn {
+                    status,
+                    stdout_lines,
+                    stderr_lines,
+                    return_value: value,
+                }) => {
+                    return_value = Some(value);
+                    stopped_at = Some(step);
+                    ShellTaskSequenceStep::Ran(ShellTaskOutput::EarlyReturn {
+                        status,
+                        stdout_lines,
+                        stderr_lines,
+                        return_value: (),
+                    })
+                }
+                Ok(ShellTaskOutput::CompleteOutput {
+                    status,
+                    stdout_lines,
+                    stderr_lines,
+                }) => ShellTaskSequenceStep::Ran(ShellTaskOutput::CompleteOutput {
+                    status,
+                    stdout_lines,
+                    stderr_lines,
+                }),
+                Err(e @ (Error::EarlyReturn(_) | Error::HandlerPanicked { .. })) => return Err(e),
+                Err(e) => ShellTaskSequenceStep::Failed(e),
+            };
+            last_succeeded = !matches!(outcome, ShellTaskSequenceStep::Failed(_));
+            steps.push(outcome);
+
+            let finished = ShellTaskSequenceEvent::StepFinished {
+                step,
+                descriptor: task.descriptor(),
+                success: last_succeeded,
+            };
+            if let ShellTaskBehavior::EarlyReturn(result) =
+                task.call_handler(&*log_handler, finished)?
+            {
+                if stopped_at.is_none() {
+                    return_value = Some(result?);
+                    stopped_at = Some(step);
+                }
+            }
+        }
+
+        if stopped_at.is_none() && !last_succeeded {
+            stopped_at = last_ran;
+        }
+
+        Ok(ShellTaskSequenceOutput {
+            steps,
+            stopped_at,
+            return_value,
+        })
+    }
+}
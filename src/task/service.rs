@@ -0,0 +1,220 @@
+use std::{
+    fmt,
+    net::{SocketAddr, TcpStream},
+    process::ExitStatus,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{unbounded, RecvTimeoutError};
+
+use crate::{Error, Result, ShellTask, ShellTaskBehavior, ShellTaskLog};
+
+use super::{collection::CollectedLines, runner::ShellTaskRunner};
+
+/// How often [`ShellTask::start_service`] checks whether a service has become ready or exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A predicate that decides whether a log line means a service is ready.
+type ReadyPredicate = dyn Fn(&ShellTaskLog) -> bool + Send + Sync + 'static;
+
+/// [`ShellTaskReadiness`] decides when a service started with [`ShellTask::start_service`] is ready.
+#[derive(Clone)]
+pub enum ShellTaskReadiness {
+    /// The service is ready once it prints a line that this predicate returns true for.
+    Line(Arc<ReadyPredicate>),
+
+    /// The service is ready once this TCP port on localhost accepts connections.
+    Port(u16),
+}
+
+impl ShellTaskReadiness {
+    /// The service is ready once it prints a line that `predicate` returns true for.
+    pub fn line<F>(predicate: F) -> Self
+    where
+        F: Fn(&ShellTaskLog) -> bool + Send + Sync + 'static,
+    {
+        ShellTaskReadiness::Line(Arc::new(predicate))
+    }
+
+    /// The service is ready once `port` on localhost accepts connections.
+    pub fn port(port: u16) -> Self {
+        ShellTaskReadiness::Port(port)
+    }
+}
+
+impl fmt::Debug for ShellTaskReadiness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShellTaskReadiness::Line(_) => write!(f, "Line(..)"),
+            ShellTaskReadiness::Port(port) => f.debug_tuple("Port").field(port).finish(),
+        }
+    }
+}
+
+/// A service started with [`ShellTask::start_service`] that keeps running until this guard is dropped,
+/// at which point it is killed.
+#[derive(Debug)]
+pub struct ShellTaskService {
+    runner: ShellTaskRunner,
+}
+
+impl ShellTaskService {
+    /// Returns the OS-assigned process identifier of the service.
+    pub fn id(&self) -> u32 {
+        self.runner.child.id()
+    }
+
+    /// Returns the exit status of the service if it has exited, without waiting for it.
+    pub fn try_wait(&mut self) -> Option<ExitStatus> {
+        self.runner.child.try_wait().ok().flatten()
+    }
+
+    /// Kills the service. This is the same as dropping it, but reads better at the end of a test.
+    pub fn stop(self) {}
+}
+
+impl ShellTask {
+    /// Starts a long-running service, like a development server, and returns once it is ready
+    /// according to `ready_when`, leaving it running in the background.
+    ///
+    /// Every line the service prints is passed to `log_handler`, both while waiting for it
+    /// to become ready and afterwards, until the returned [`ShellTaskService`] is dropped and the service is killed.
+    ///
+    /// Returns [`Error::ServiceExited`] if the service exits before it is ready, and
+    /// [`Error::TimedOut`] if it is not ready within [`ShellTask::timeout`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use shell_candy::{ShellTask, ShellTaskLog, ShellTaskReadiness};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    ///     let server = ShellTask::shell("echo starting; echo ready; exec sleep 30")?.start_service(
+    ///         ShellTaskReadiness::line(|line| matches!(line, ShellTaskLog::Stdout(line) if line == "ready")),
+    ///         |line| eprintln!("server: {:?}", line),
+    ///     )?;
+    ///     // run tests against the server here; it is killed when `server` is dropped
+    ///     server.stop();
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn start_service<F>(
+        &self,
+        ready_when: ShellTaskReadiness,
+        log_handler: F,
+    ) -> Result<ShellTaskService>
+    where
+        F: Fn(ShellTaskLog) + Send + Sync + 'static,
+    {
+        let (log_sender, log_receiver) = unbounded();
+        let mut runner = ShellTaskRunner::run(
+            self.get_command()?,
+            self.descriptor(),
+            self.process_group,
+            log_sender,
+            &self.stdin,
+        )?;
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let forward = move |line| {
+            log_handler(line);
+            ShellTaskBehavior::<()>::Passthrough
+        };
+
+        let mut collected_stdout_lines = CollectedLines::new(self.collection);
+        let mut collected_stderr_lines = CollectedLines::new(self.collection);
+        let mut logs_done = false;
+        loop {
+            if let ShellTaskReadiness::Port(port) = ready_when {
+                if accepts_connections(port) {
+                    break;
+                }
+            }
+
+            if logs_done {
+                thread::sleep(POLL_INTERVAL);
+            } else {
+                match log_receiver.recv_timeout(POLL_INTERVAL) {
+                    Ok(Ok((_, line))) => {
+                        let line = self.map_log(line);
+                        match &line {
+                            ShellTaskLog::Stderr(stderr) => collected_stderr_lines.push(stderr),
+                            ShellTaskLog::Stdout(stdout) => collected_stdout_lines.push(stdout),
+                        }
+                        let is_ready = match &ready_when {
+                            ShellTaskReadiness::Line(predicate) => predicate(&line),
+                            ShellTaskReadiness::Port(_) => false,
+                        };
+                        if let Err(e) = self.call_handler(&forward, line) {
+                            runner.kill();
+                            return Err(e);
+                        }
+                        if is_ready {
+                            break;
+                        }
+                        continue;
+                    }
+                    Ok(Err(e)) => {
+                        runner.kill();
+                        return Err(e);
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => logs_done = true,
+                }
+            }
+
+            // only give up once every line has been read, since the line that means the service is ready
+            // may still be in the pipe when it exits
+            if logs_done {
+                let status = runner
+                    .child
+                    .try_wait()
+                    .map_err(|source| Error::CouldNotWait {
+                        task: self.descriptor(),
+                        source,
+                    })?;
+                if let Some(exit_status) = status {
+                    return Err(Error::ServiceExited {
+                        task: self.descriptor(),
+                        exit_status,
+                        stdout_lines: self.failure_output(collected_stdout_lines.into_vec()),
+                        stderr_lines: self.failure_output(collected_stderr_lines.into_vec()),
+                    });
+                }
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                runner.kill();
+                return Err(self.timed_out(collected_stdout_lines, collected_stderr_lines));
+            }
+        }
+
+        // keep streaming logs until the service is killed and its pipes close
+        let task = self.clone();
+        thread::spawn(move || {
+            let mut handler_panicked = false;
+            for log in log_receiver {
+                let Ok((_, line)) = log else {
+                    break;
+                };
+                // a panicking log handler stops receiving logs, but the service keeps running until it is dropped,
+                // so its output is still drained to keep it from blocking on a full pipe
+                if !handler_panicked {
+                    handler_panicked = task.call_handler(&forward, task.map_log(line)).is_err();
+                }
+            }
+        });
+
+        Ok(ShellTaskService { runner })
+    }
+}
+
+/// Returns true if `port` on localhost accepts TCP connections over either IPv4 or IPv6.
+fn accepts_connections(port: u16) -> bool {
+    [
+        SocketAddr::from(([127, 0, 0, 1], port)),
+        SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 1], port)),
+    ]
+    .iter()
+    .any(|address| TcpStream::connect_timeout(address, POLL_INTERVAL).is_ok())
+}
@@ -0,0 +1,34 @@
+#[cfg(doc)]
+use crate::ShellTask;
+
+/// [`ShellTaskStdin`] decides what a [`ShellTask`] reads from `stdin`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ShellTaskStdin {
+    /// The task inherits `stdin` from the current process.
+    #[default]
+    Inherit,
+
+    /// The task reads from an empty `stdin`, like `< /dev/null`.
+    Null,
+
+    /// The task reads these bytes from `stdin`, which is closed once they have all been written.
+    Bytes(Vec<u8>),
+}
+
+impl From<&str> for ShellTaskStdin {
+    fn from(input: &str) -> Self {
+        ShellTaskStdin::Bytes(input.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for ShellTaskStdin {
+    fn from(input: String) -> Self {
+        ShellTaskStdin::Bytes(input.into_bytes())
+    }
+}
+
+impl From<Vec<u8>> for ShellTaskStdin {
+    fn from(input: Vec<u8>) -> Self {
+        ShellTaskStdin::Bytes(input)
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+use semver::{Version, VersionReq};
+
+use crate::{Error, Result, ShellTask, ShellTaskBehavior, ShellTaskOutput};
+
+/// Versions found by [`ShellTask::require_version`], keyed by binary and version flag.
+static VERSIONS: OnceLock<Mutex<HashMap<(String, String), Version>>> = OnceLock::new();
+
+impl ShellTask {
+    /// Runs `bin` with `version_flag`, extracts the first semver-looking token from its output,
+    /// and checks it against `requirement`. Returns the version that was found.
+    ///
+    /// Versions are cached per binary and flag, so checking the same tool again does not rerun it.
+    /// Versions with only a major and minor component, like `1.60`, are treated as `1.60.0`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use shell_candy::ShellTask;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    ///     let rustc_version = ShellTask::require_version("rustc", "--version", ">=1.60")?;
+    ///     assert_eq!(rustc_version.major, 1);
+    ///     assert!(ShellTask::require_version("rustc", "--version", "<1.0").is_err());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn require_version(bin: &str, version_flag: &str, requirement: &str) -> Result<Version> {
+        let command = format!("{} {}", bin, version_flag);
+        let required =
+            VersionReq::parse(requirement).map_err(|source| Error::InvalidVersionRequirement {
+                requirement: requirement.to_string(),
+                source,
+            })?;
+
+        let versions = VERSIONS.get_or_init(Default::default);
+        let key = (bin.to_string(), version_flag.to_string());
+        let cached = versions
+            .lock()
+            .ok()
+            .and_then(|versions| versions.get(&key).cloned());
+        let found = match cached {
+            Some(found) => found,
+            None => {
+                let found = find_version(&command)?;
+                if let Ok(mut versions) = versions.lock() {
+                    versions.insert(key, found.clone());
+                }
+                found
+            }
+        };
+
+        if required.matches(&found) {
+            Ok(found)
+        } else {
+            Err(Error::UnsupportedVersion {
+                task: command,
+                found,
+                required,
+            })
+        }
+    }
+}
+
+/// Runs `command` and returns the first version in its `stdout`, falling back to its `stderr`.
+fn find_version(command: &str) -> Result<Version> {
+    let (stdout_lines, stderr_lines) =
+        match ShellTask::new(command)?.run(|_| ShellTaskBehavior::<()>::Passthrough)? {
+            ShellTaskOutput::CompleteOutput {
+                stdout_lines,
+                stderr_lines,
+                ..
+            }
+            | ShellTaskOutput::EarlyReturn {
+                stdout_lines,
+                stderr_lines,
+                ..
+            } => (stdout_lines, stderr_lines),
+        };
+    stdout_lines
+        .iter()
+        .chain(stderr_lines.iter())
+        .flat_map(|line| line.split(|c: char| c.is_whitespace() || matches!(c, ',' | '(' | ')')))
+        .find_map(parse_version)
+        .ok_or_else(|| Error::VersionNotFound {
+            task: command.to_string(),
+        })
+}
+
+/// Parses a token like `1.63.0`, `v18.12.1`, or `1.60` into a [`Version`].
+fn parse_version(token: &str) -> Option<Version> {
+    let token = token.strip_prefix('v').unwrap_or(token);
+    let core_len = token
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(token.len());
+    let (core, suffix) = token.split_at(core_len);
+    let core = core.trim_end_matches('.');
+    let components = core.split('.').count();
+    if core.split('.').any(|c| c.is_empty()) || !(2..=3).contains(&components) {
+        return None;
+    }
+    let suffix = if suffix.starts_with(['-', '+']) {
+        suffix
+    } else {
+        ""
+    };
+    let padding = if components == 2 { ".0" } else { "" };
+    Version::parse(&format!("{}{}{}", core, padding, suffix))
+        .or_else(|_| Version::parse(&format!("{}{}", core, padding)))
+        .ok()
+}
@@ -0,0 +1,43 @@
+use shell_candy::{Error, ShellTask, ShellTaskBehavior, ShellTaskReadiness};
+
+fn missing_directory_task() -> ShellTask {
+    let mut task = ShellTask::new("true").unwrap();
+    task.current_dir(std::env::temp_dir().join("shell-candy-missing-directory"));
+    task
+}
+
+fn assert_not_a_directory(error: Error) {
+    match error {
+        Error::InvalidTask { task, reason } => {
+            assert_eq!(task, "true");
+            assert!(reason.ends_with("is not a directory"), "{}", reason);
+        }
+        error => panic!("expected Error::InvalidTask, got {:?}", error),
+    }
+}
+
+#[test]
+fn run_reports_a_missing_directory() {
+    let error = missing_directory_task()
+        .run(|_| ShellTaskBehavior::<()>::Passthrough)
+        .unwrap_err();
+    assert_not_a_directory(error);
+}
+
+#[test]
+fn start_service_reports_a_missing_directory() {
+    let error = missing_directory_task()
+        .start_service(ShellTaskReadiness::port(1), |_| {})
+        .unwrap_err();
+    assert_not_a_directory(error);
+}
+
+#[test]
+fn pipelines_report_a_missing_directory() {
+    let error = ShellTask::new("echo hi")
+        .unwrap()
+        .pipe(missing_directory_task())
+        .run(|_| ShellTaskBehavior::<()>::Passthrough)
+        .unwrap_err();
+    assert_not_a_directory(error);
+}
@@ -0,0 +1,32 @@
+use shell_candy::{ShellTask, ShellTaskBuilder, ShellTaskGroup};
+
+const SECRET: &str = "hunter2-token";
+
+fn task_with_secrets() -> ShellTask {
+    let mut task = ShellTask::new("curl --header").unwrap();
+    task.secret_arg(format!("Authorization: Bearer {}", SECRET))
+        .secret_env("API_TOKEN", SECRET)
+        .stdin(format!("password={}", SECRET));
+    task
+}
+
+#[test]
+fn task_debug_does_not_print_secrets() {
+    let debug = format!("{:?}", task_with_secrets());
+    assert!(!debug.contains(SECRET), "{}", debug);
+    assert!(debug.contains("API_TOKEN"), "{}", debug);
+}
+
+#[test]
+fn wrapper_debug_does_not_print_secrets() {
+    let pipeline = task_with_secrets().pipe(ShellTask::new("cat").unwrap());
+    let group = ShellTaskGroup::new([task_with_secrets()]);
+    let builder = ShellTaskBuilder::new("curl").secret_env("API_TOKEN", SECRET);
+    for debug in [
+        format!("{:?}", pipeline),
+        format!("{:?}", group),
+        format!("{:?}", builder),
+    ] {
+        assert!(!debug.contains(SECRET), "{}", debug);
+    }
+}
@@ -0,0 +1,53 @@
+use shell_candy::{Error, ShellTask, ShellTaskBehavior, ShellTaskLog, ShellTaskOutput};
+
+/// A task that exits unsuccessfully whether or not it is killed after printing `stop`.
+fn failing_task() -> ShellTask {
+    ShellTask::shell("echo stop; exit 3").unwrap()
+}
+
+fn is_stop(log: &ShellTaskLog) -> bool {
+    matches!(log, ShellTaskLog::Stdout(line) if line == "stop")
+}
+
+#[test]
+fn early_return_value_wins_over_a_failed_exit_status() {
+    let output = failing_task()
+        .run(|log| {
+            if is_stop(&log) {
+                ShellTaskBehavior::EarlyReturn(Ok(42))
+            } else {
+                ShellTaskBehavior::Passthrough
+            }
+        })
+        .unwrap();
+    match output {
+        ShellTaskOutput::EarlyReturn {
+            status,
+            stdout_lines,
+            return_value,
+            ..
+        } => {
+            assert_eq!(return_value, 42);
+            assert!(!status.success());
+            assert_eq!(stdout_lines, vec!["stop".to_string()]);
+        }
+        output => panic!("expected ShellTaskOutput::EarlyReturn, got {:?}", output),
+    }
+}
+
+#[test]
+fn early_return_error_wins_over_a_failed_exit_status() {
+    let error = failing_task()
+        .run(|log| {
+            if is_stop(&log) {
+                ShellTaskBehavior::<()>::EarlyReturn(Err("stopped by the handler".into()))
+            } else {
+                ShellTaskBehavior::Passthrough
+            }
+        })
+        .unwrap_err();
+    match error {
+        Error::EarlyReturn(e) => assert_eq!(e.to_string(), "stopped by the handler"),
+        error => panic!("expected Error::EarlyReturn, got {:?}", error),
+    }
+}
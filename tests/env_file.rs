@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+
+use shell_candy::{Error, ShellTask, ShellTaskBehavior, ShellTaskOutput};
+
+fn write_env_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("shell-candy-{}.env", name));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn malformed_lines_are_reported_with_their_line_number() {
+    let path = write_env_file("malformed", "# comment\nA=1\nB='never closed\n");
+    let error = ShellTask::new("true")
+        .unwrap()
+        .env_file(&path)
+        .map(|_| ())
+        .unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+    match error {
+        Error::InvalidEnvFile {
+            path: error_path,
+            line,
+            reason,
+        } => {
+            assert_eq!(error_path, path);
+            assert_eq!(line, 3);
+            assert!(reason.contains("never closed"), "{}", reason);
+        }
+        error => panic!("expected Error::InvalidEnvFile, got {:?}", error),
+    }
+}
+
+#[test]
+fn references_fall_back_to_the_task_env() {
+    let path = write_env_file("fallback", "GREETING=\"hello ${NAME}\"\n");
+    let mut task = ShellTask::new("printenv GREETING").unwrap();
+    task.env("NAME", "task");
+    task.env_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let output = task.run(|_| ShellTaskBehavior::<()>::Passthrough).unwrap();
+    match output {
+        ShellTaskOutput::CompleteOutput { stdout_lines, .. } => {
+            assert_eq!(stdout_lines, vec!["hello task".to_string()])
+        }
+        output => panic!("expected ShellTaskOutput::CompleteOutput, got {:?}", output),
+    }
+}
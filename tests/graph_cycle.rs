@@ -0,0 +1,99 @@
+use shell_candy::{Error, ShellTask, ShellTaskBehavior, ShellTaskGraph};
+
+fn task(name: &str) -> ShellTask {
+    ShellTask::new(&format!("echo {}", name)).unwrap()
+}
+
+fn run(graph: &ShellTaskGraph) -> Error {
+    graph
+        .run(|_| ShellTaskBehavior::<()>::Passthrough)
+        .map(|_| ())
+        .unwrap_err()
+}
+
+fn cycle(error: Error) -> Vec<String> {
+    match error {
+        Error::DependencyCycle { cycle } => cycle,
+        error => panic!("expected Error::DependencyCycle, got {:?}", error),
+    }
+}
+
+#[test]
+fn self_dependency_is_a_cycle() {
+    let mut graph = ShellTaskGraph::new();
+    let x = graph.add(task("x")).id();
+    graph.node(x).after(x);
+    let error = run(&graph);
+    assert_eq!(
+        error.to_string(),
+        "the task graph contains a dependency cycle: 'echo x' -> 'echo x'."
+    );
+    assert_eq!(cycle(error), vec!["echo x".to_string()]);
+}
+
+#[test]
+fn three_task_cycle_is_listed_in_run_order() {
+    let mut graph = ShellTaskGraph::new();
+    let a = graph.add(task("a")).id();
+    let b = graph.add(task("b")).after(a).id();
+    let c = graph.add(task("c")).after(b).id();
+    graph.node(a).after(c);
+    let error = run(&graph);
+    assert_eq!(
+        error.to_string(),
+        "the task graph contains a dependency cycle: 'echo a' -> 'echo b' -> 'echo c' -> 'echo a'."
+    );
+    assert_eq!(
+        cycle(error),
+        vec![
+            "echo a".to_string(),
+            "echo b".to_string(),
+            "echo c".to_string()
+        ]
+    );
+}
+
+#[test]
+fn dependents_of_a_cycle_are_not_part_of_it() {
+    let mut graph = ShellTaskGraph::new();
+    // added first, so cycle detection starts from a task that only depends on the cycle
+    let downstream = graph.add(task("downstream")).id();
+    let independent = graph.add(task("independent")).id();
+    let b = graph.add(task("b")).after(independent).id();
+    let a = graph.add(task("a")).id();
+    graph.node(b).after(a);
+    graph.node(a).after(b);
+    graph.node(downstream).after(a).after(independent);
+    assert_eq!(
+        cycle(run(&graph)),
+        vec!["echo b".to_string(), "echo a".to_string()]
+    );
+}
+
+#[test]
+fn dependency_from_another_graph_is_invalid() {
+    // the foreign id has the same index as a task in this graph, so only the graph it came from tells them apart
+    let mut other = ShellTaskGraph::new();
+    let foreign = other.add(task("other")).id();
+
+    let mut graph = ShellTaskGraph::new();
+    graph.add(task("x")).after(foreign);
+    match run(&graph) {
+        Error::InvalidTask { task, reason } => {
+            assert_eq!(task, "echo x");
+            assert!(reason.contains("not part of this graph"), "{}", reason);
+        }
+        error => panic!("expected Error::InvalidTask, got {:?}", error),
+    }
+}
+
+#[test]
+#[should_panic(expected = "is not part of this graph")]
+fn node_from_another_graph_panics() {
+    let mut other = ShellTaskGraph::new();
+    let foreign = other.add(task("other")).id();
+
+    let mut graph = ShellTaskGraph::new();
+    graph.add(task("x"));
+    graph.node(foreign);
+}
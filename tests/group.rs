@@ -0,0 +1,98 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use shell_candy::{Error, ShellTask, ShellTaskBehavior, ShellTaskGroup, ShellTaskLog};
+
+fn shell(script: &str) -> ShellTask {
+    ShellTask::shell(script).unwrap()
+}
+
+#[test]
+fn fail_fast_kills_running_tasks() {
+    let started = Instant::now();
+    let output = ShellTaskGroup::new([shell("exec sleep 30"), shell("sleep 0.2; exit 1")])
+        .max_concurrency(2)
+        .fail_fast(true)
+        .run(|_| ShellTaskBehavior::<()>::Passthrough);
+    assert!(started.elapsed() < Duration::from_secs(10));
+    assert!(
+        matches!(output.results[0].result, Err(Error::Cancelled { ref task }) if task == "exec sleep 30"),
+        "expected Error::Cancelled, got {:?}",
+        output.results[0].result
+    );
+    assert!(matches!(
+        output.results[1].result,
+        Err(Error::TaskFailure { .. })
+    ));
+}
+
+#[test]
+fn fail_fast_skips_tasks_that_have_not_started() {
+    let output = ShellTaskGroup::new([shell("exit 1"), shell("echo never")])
+        .max_concurrency(1)
+        .fail_fast(true)
+        .run(|line| {
+            assert_ne!(line.log, ShellTaskLog::Stdout("never".to_string()));
+            ShellTaskBehavior::<()>::Passthrough
+        });
+    assert!(matches!(
+        output.results[0].result,
+        Err(Error::TaskFailure { .. })
+    ));
+    assert!(matches!(
+        output.results[1].result,
+        Err(Error::Cancelled { .. })
+    ));
+}
+
+#[test]
+fn early_return_cancels_the_rest_with_fail_fast() {
+    let output = ShellTaskGroup::new([shell("echo stop"), shell("echo never")])
+        .max_concurrency(1)
+        .fail_fast(true)
+        .run(|_| ShellTaskBehavior::EarlyReturn(Ok(())));
+    assert!(output.results[0].result.is_ok());
+    assert!(matches!(
+        output.results[1].result,
+        Err(Error::Cancelled { .. })
+    ));
+}
+
+#[test]
+fn without_fail_fast_every_task_runs() {
+    let output = ShellTaskGroup::new([shell("exit 1"), shell("echo still runs")])
+        .max_concurrency(1)
+        .run(|_| ShellTaskBehavior::<()>::Passthrough);
+    assert!(!output.is_success());
+    assert!(output.results[0].result.is_err());
+    assert!(output.get("echo still runs").unwrap().is_ok());
+}
+
+#[test]
+fn max_concurrency_limits_running_tasks() {
+    let running = Arc::new(AtomicUsize::new(0));
+    let most_running = Arc::new(AtomicUsize::new(0));
+    let (handler_running, handler_most_running) = (running.clone(), most_running.clone());
+    let output = ShellTaskGroup::new((0..5).map(|_| shell("echo start; sleep 0.2; echo end")))
+        .max_concurrency(2)
+        .run(move |line| {
+            match line.log {
+                ShellTaskLog::Stdout(line) if line == "start" => {
+                    let now = handler_running.fetch_add(1, Ordering::SeqCst) + 1;
+                    handler_most_running.fetch_max(now, Ordering::SeqCst);
+                }
+                ShellTaskLog::Stdout(line) if line == "end" => {
+                    handler_running.fetch_sub(1, Ordering::SeqCst);
+                }
+                _ => {}
+            }
+            ShellTaskBehavior::<()>::Passthrough
+        });
+    assert!(output.is_success());
+    assert_eq!(most_running.load(Ordering::SeqCst), 2);
+}
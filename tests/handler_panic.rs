@@ -0,0 +1,35 @@
+use shell_candy::{Error, ShellTask, ShellTaskBehavior, ShellTaskLog};
+
+fn panic_on_line(target: &'static str) -> Error {
+    let task = ShellTask::new("printf first\\nmiddle\\nlast\\n").unwrap();
+    task.run(move |line| match line {
+        ShellTaskLog::Stdout(message) if message == target => panic!("bad line: {}", message),
+        _ => ShellTaskBehavior::<()>::Passthrough,
+    })
+    .unwrap_err()
+}
+
+fn assert_handler_panicked(error: Error, expected_message: &str) {
+    match error {
+        Error::HandlerPanicked { task, message } => {
+            assert_eq!(task, "printf first\\nmiddle\\nlast\\n");
+            assert_eq!(message, expected_message);
+        }
+        error => panic!("expected Error::HandlerPanicked, got {:?}", error),
+    }
+}
+
+#[test]
+fn handler_panics_on_first_line() {
+    assert_handler_panicked(panic_on_line("first"), "bad line: first");
+}
+
+#[test]
+fn handler_panics_on_middle_line() {
+    assert_handler_panicked(panic_on_line("middle"), "bad line: middle");
+}
+
+#[test]
+fn handler_panics_on_last_line() {
+    assert_handler_panicked(panic_on_line("last"), "bad line: last");
+}
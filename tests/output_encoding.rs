@@ -0,0 +1,46 @@
+use shell_candy::{ShellTask, ShellTaskBehavior, ShellTaskOutput};
+
+fn stdout_lines(script: &str) -> Vec<String> {
+    let output = ShellTask::shell(script)
+        .unwrap()
+        .run(|_| ShellTaskBehavior::<()>::Passthrough)
+        .unwrap();
+    match output {
+        ShellTaskOutput::CompleteOutput { stdout_lines, .. } => stdout_lines,
+        output => panic!("expected ShellTaskOutput::CompleteOutput, got {:?}", output),
+    }
+}
+
+#[test]
+fn invalid_utf8_is_decoded_lossily() {
+    assert_eq!(
+        stdout_lines("printf 'ok\\n\\377\\376bad\\nafter\\n'"),
+        vec![
+            "ok".to_string(),
+            "\u{FFFD}\u{FFFD}bad".to_string(),
+            "after".to_string()
+        ]
+    );
+}
+
+#[test]
+fn latin1_on_stderr_does_not_fail_the_task() {
+    let output = ShellTask::shell("printf 'caf\\351\\n' >&2")
+        .unwrap()
+        .run(|_| ShellTaskBehavior::<()>::Passthrough)
+        .unwrap();
+    match output {
+        ShellTaskOutput::CompleteOutput { stderr_lines, .. } => {
+            assert_eq!(stderr_lines, vec!["caf\u{FFFD}".to_string()])
+        }
+        output => panic!("expected ShellTaskOutput::CompleteOutput, got {:?}", output),
+    }
+}
+
+#[test]
+fn crlf_and_missing_final_newline_are_handled() {
+    assert_eq!(
+        stdout_lines("printf 'one\\r\\ntwo'"),
+        vec!["one".to_string(), "two".to_string()]
+    );
+}
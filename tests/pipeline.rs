@@ -0,0 +1,71 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use shell_candy::{Error, ShellTask, ShellTaskBehavior, ShellTaskLog, ShellTaskOutput};
+
+fn shell(script: &str) -> ShellTask {
+    ShellTask::shell(script).unwrap()
+}
+
+#[test]
+fn failure_names_the_rightmost_failing_stage() {
+    let error = shell("exit 2")
+        .pipe(shell("exit 3"))
+        .pipe(shell("cat"))
+        .run(|_| ShellTaskBehavior::<()>::Passthrough)
+        .unwrap_err();
+    assert_eq!(error.exit_code(), Some(3));
+    assert!(
+        error.to_string().contains("failed at stage 1:"),
+        "{}",
+        error
+    );
+    match error {
+        Error::PipelineFailure { stage, source, .. } => {
+            assert_eq!(stage, 1);
+            assert!(matches!(*source, Error::TaskFailure { .. }));
+        }
+        error => panic!("expected Error::PipelineFailure, got {:?}", error),
+    }
+}
+
+#[test]
+fn logs_are_tagged_with_their_stage() {
+    let logs = Arc::new(Mutex::new(Vec::new()));
+    let handler_logs = logs.clone();
+    shell("echo zero >&2; echo passed")
+        .pipe(shell("cat; echo one >&2"))
+        .run(move |line| {
+            handler_logs.lock().unwrap().push((line.stage, line.log));
+            ShellTaskBehavior::<()>::Passthrough
+        })
+        .unwrap();
+    let mut logs = logs.lock().unwrap().clone();
+    // stdout and stderr are read separately, so only the order within each stream is guaranteed
+    logs.sort_by_key(|(stage, log)| (*stage, format!("{:?}", log)));
+    // stdout of every stage but the last is piped to the next stage instead of the log handler
+    assert_eq!(
+        logs,
+        vec![
+            (0, ShellTaskLog::Stderr("zero".to_string())),
+            (1, ShellTaskLog::Stderr("one".to_string())),
+            (1, ShellTaskLog::Stdout("passed".to_string())),
+        ]
+    );
+}
+
+#[test]
+fn early_return_kills_every_stage() {
+    let started = Instant::now();
+    let output = shell("echo go >&2; exec sleep 30")
+        .pipe(shell("exec sleep 30"))
+        .run(|line| match line.log {
+            ShellTaskLog::Stderr(line) if line == "go" => ShellTaskBehavior::EarlyReturn(Ok(())),
+            _ => ShellTaskBehavior::Passthrough,
+        })
+        .unwrap();
+    assert!(started.elapsed() < Duration::from_secs(10));
+    assert!(matches!(output, ShellTaskOutput::EarlyReturn { .. }));
+}
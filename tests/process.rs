@@ -0,0 +1,99 @@
+use std::{
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use shell_candy::{
+    Error, ShellTask, ShellTaskBehavior, ShellTaskLog, ShellTaskOutput, ShellTaskReadiness,
+};
+
+/// Returns true if `pid` is alive. Killed children that are not reaped yet linger as zombies, which do not count.
+fn is_running(pid: u32) -> bool {
+    let script = format!(
+        "kill -0 {pid} 2>/dev/null && ! grep -qs '^State:.*Z' /proc/{pid}/status",
+        pid = pid
+    );
+    ShellTask::shell(&script)
+        .unwrap()
+        .run(|_| ShellTaskBehavior::<()>::Passthrough)
+        .is_ok()
+}
+
+fn stops_running(pid: u32) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        if !is_running(pid) {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    false
+}
+
+fn ready_line() -> ShellTaskReadiness {
+    ShellTaskReadiness::line(|line| matches!(line, ShellTaskLog::Stdout(line) if line == "ready"))
+}
+
+#[test]
+fn dropping_a_service_with_a_process_group_kills_its_background_children() {
+    let mut task = ShellTask::shell("sleep 30 & echo $!; echo ready; wait").unwrap();
+    task.process_group(true);
+    let (pid_sender, pid_receiver) = mpsc::channel();
+    let service = task
+        .start_service(ready_line(), move |line| {
+            if let ShellTaskLog::Stdout(line) = line {
+                if let Ok(pid) = line.parse::<u32>() {
+                    let _ = pid_sender.send(pid);
+                }
+            }
+        })
+        .unwrap();
+    let grandchild = pid_receiver.recv().unwrap();
+    assert!(is_running(grandchild));
+    drop(service);
+    assert!(stops_running(grandchild));
+}
+
+#[test]
+fn timing_out_with_a_process_group_kills_background_children() {
+    let mut task = ShellTask::shell("sleep 30 & echo $!; wait").unwrap();
+    task.process_group(true).timeout(Duration::from_millis(300));
+    let error = task
+        .run(|_| ShellTaskBehavior::<()>::Passthrough)
+        .unwrap_err();
+    let Error::TimedOut { stdout_lines, .. } = error else {
+        panic!("expected Error::TimedOut, got {:?}", error);
+    };
+    let grandchild: u32 = stdout_lines[0].parse().unwrap();
+    assert!(stops_running(grandchild));
+}
+
+#[test]
+fn tasks_that_die_with_their_parent_still_run() {
+    let mut task = ShellTask::new("echo hello").unwrap();
+    task.die_with_parent(true);
+    match task.run(|_| ShellTaskBehavior::<()>::Passthrough).unwrap() {
+        ShellTaskOutput::CompleteOutput { stdout_lines, .. } => {
+            assert_eq!(stdout_lines, vec!["hello".to_string()])
+        }
+        output => panic!("expected ShellTaskOutput::CompleteOutput, got {:?}", output),
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn tasks_that_die_with_their_parent_are_killed_when_the_spawning_thread_exits() {
+    let pid = thread::spawn(|| {
+        let mut task = ShellTask::shell("echo ready; exec sleep 30").unwrap();
+        task.die_with_parent(true);
+        let service = task.start_service(ready_line(), |_| {}).unwrap();
+        let pid = service.id();
+        // leaking the service keeps it from being killed on drop, so only the death signal can stop it
+        std::mem::forget(service);
+        pid
+    })
+    .join()
+    .unwrap();
+    assert!(stops_running(pid));
+}
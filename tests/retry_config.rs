@@ -0,0 +1,109 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use shell_candy::{
+    Error, RetryBackoff, RetryPolicy, ShellTask, ShellTaskBehavior, ShellTaskRetryEvent,
+};
+
+fn policy() -> RetryPolicy {
+    RetryPolicy {
+        attempts: 3,
+        backoff: RetryBackoff::Fixed(Duration::from_millis(1)),
+        ..Default::default()
+    }
+}
+
+fn missing_directory_task() -> ShellTask {
+    let mut task = ShellTask::new("true").unwrap();
+    task.current_dir(std::env::temp_dir().join("shell-candy-missing-directory"));
+    task
+}
+
+/// Runs `task` with `policy`, returning the error and how many attempts were started.
+fn run_failing<F>(task: &ShellTask, policy: RetryPolicy, log_handler: F) -> (Error, usize)
+where
+    F: Fn(&ShellTaskRetryEvent) -> ShellTaskBehavior<()> + Send + Sync + 'static,
+{
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let counted = attempts.clone();
+    let error = task
+        .retry(policy)
+        .run(move |event| {
+            if let ShellTaskRetryEvent::AttemptStarted { .. } = event {
+                counted.fetch_add(1, Ordering::SeqCst);
+            }
+            log_handler(&event)
+        })
+        .unwrap_err();
+    (error, attempts.load(Ordering::SeqCst))
+}
+
+#[test]
+fn configuration_errors_are_not_retried_by_default() {
+    let (error, attempts) = run_failing(&missing_directory_task(), policy(), |_| {
+        ShellTaskBehavior::Passthrough
+    });
+    assert_eq!(attempts, 1);
+    match error {
+        Error::RetryFailure {
+            attempts: 1,
+            source,
+            ..
+        } => assert!(
+            matches!(*source, Error::InvalidTask { .. }),
+            "expected Error::InvalidTask, got {:?}",
+            source
+        ),
+        error => panic!("expected Error::RetryFailure, got {:?}", error),
+    }
+}
+
+#[test]
+fn retry_if_decides_for_configuration_errors() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counted = calls.clone();
+    let policy = policy().retry_if(move |_, _, _| {
+        counted.fetch_add(1, Ordering::SeqCst);
+        true
+    });
+    let (error, attempts) = run_failing(&missing_directory_task(), policy, |_| {
+        ShellTaskBehavior::Passthrough
+    });
+    assert_eq!(attempts, 3);
+    // the last attempt is not retried no matter what, so retry_if is not asked about it
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    assert!(matches!(error, Error::RetryFailure { attempts: 3, .. }));
+}
+
+#[test]
+fn early_returns_are_not_retried_but_report_attempts() {
+    let policy = policy().retry_if(|_, _, _| panic!("retry_if is never asked about early returns"));
+    let (error, attempts) = run_failing(
+        &ShellTask::shell("echo stop; exit 1").unwrap(),
+        policy,
+        |event| match event {
+            ShellTaskRetryEvent::Log { .. } => {
+                ShellTaskBehavior::EarlyReturn(Err("stopped".into()))
+            }
+            ShellTaskRetryEvent::AttemptStarted { .. } => ShellTaskBehavior::Passthrough,
+        },
+    );
+    assert_eq!(attempts, 1);
+    match error {
+        Error::RetryFailure {
+            attempts: 1,
+            source,
+            ..
+        } => assert!(
+            matches!(*source, Error::EarlyReturn(ref e) if e.to_string() == "stopped"),
+            "expected Error::EarlyReturn, got {:?}",
+            source
+        ),
+        error => panic!("expected Error::RetryFailure, got {:?}", error),
+    }
+}
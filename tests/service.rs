@@ -0,0 +1,133 @@
+use std::{
+    net::TcpListener,
+    path::Path,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use shell_candy::{Error, ShellTask, ShellTaskBehavior, ShellTaskLog, ShellTaskReadiness};
+
+fn ready_line() -> ShellTaskReadiness {
+    ShellTaskReadiness::line(|line| matches!(line, ShellTaskLog::Stdout(line) if line == "ready"))
+}
+
+fn is_running(pid: u32) -> bool {
+    ShellTask::shell(&format!("kill -0 {} 2>/dev/null", pid))
+        .unwrap()
+        .run(|_| ShellTaskBehavior::<()>::Passthrough)
+        .is_ok()
+}
+
+fn wait_for(mut condition: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        if condition() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    false
+}
+
+#[test]
+fn port_readiness_waits_until_the_port_accepts_connections() {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let (stop_sender, stop_receiver) = mpsc::channel::<()>();
+    let listener = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(300));
+        let _listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+        let _ = stop_receiver.recv();
+    });
+
+    let started = Instant::now();
+    let mut task = ShellTask::shell("exec sleep 30").unwrap();
+    task.timeout(Duration::from_secs(10));
+    let service = task
+        .start_service(ShellTaskReadiness::port(port), |_| {})
+        .unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(300));
+    service.stop();
+
+    stop_sender.send(()).unwrap();
+    listener.join().unwrap();
+}
+
+#[test]
+fn exiting_before_ready_is_service_exited() {
+    let error = ShellTask::shell("echo starting; echo failed >&2; exit 3")
+        .unwrap()
+        .start_service(ready_line(), |_| {})
+        .unwrap_err();
+    match error {
+        Error::ServiceExited {
+            exit_status,
+            stdout_lines,
+            stderr_lines,
+            ..
+        } => {
+            assert_eq!(exit_status.code(), Some(3));
+            assert_eq!(stdout_lines, vec!["starting".to_string()]);
+            assert_eq!(stderr_lines, vec!["failed".to_string()]);
+        }
+        error => panic!("expected Error::ServiceExited, got {:?}", error),
+    }
+}
+
+#[test]
+fn not_ready_within_the_timeout_is_timed_out() {
+    let mut task = ShellTask::shell("echo starting; exec sleep 30").unwrap();
+    task.timeout(Duration::from_millis(200));
+    match task.start_service(ready_line(), |_| {}).unwrap_err() {
+        Error::TimedOut {
+            timeout,
+            stdout_lines,
+            ..
+        } => {
+            assert_eq!(timeout, Duration::from_millis(200));
+            assert_eq!(stdout_lines, vec!["starting".to_string()]);
+        }
+        error => panic!("expected Error::TimedOut, got {:?}", error),
+    }
+}
+
+#[test]
+fn dropping_the_service_kills_it() {
+    let service = ShellTask::shell("echo ready; exec sleep 30")
+        .unwrap()
+        .start_service(ready_line(), |_| {})
+        .unwrap();
+    let pid = service.id();
+    assert!(is_running(pid));
+    drop(service);
+    assert!(!is_running(pid));
+}
+
+#[test]
+fn output_is_drained_after_the_log_handler_panics() {
+    let done = std::env::temp_dir().join(format!(
+        "shell-candy-service-drained-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&done);
+    // far more output than fits in a pipe, so the service blocks unless it is read
+    let script = format!(
+        "echo ready; yes line | head -n 200000 && touch '{}'; exec sleep 30",
+        done.display()
+    );
+    let service = ShellTask::shell(&script)
+        .unwrap()
+        .start_service(ready_line(), |line| {
+            if matches!(line, ShellTaskLog::Stdout(line) if line == "line") {
+                panic!("bad handler");
+            }
+        })
+        .unwrap();
+    assert!(wait_for(|| Path::new(&done).exists()));
+    service.stop();
+    std::fs::remove_file(&done).unwrap();
+}
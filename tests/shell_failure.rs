@@ -0,0 +1,48 @@
+use shell_candy::{Error, ShellTask, ShellTaskBehavior};
+
+fn run_shell(shell: &str, script: &str) -> Error {
+    ShellTask::shell_with(shell, script)
+        .unwrap()
+        .run(|_| ShellTaskBehavior::<()>::Passthrough)
+        .unwrap_err()
+}
+
+fn shell_failure_reason(error: &Error) -> Option<&str> {
+    match error {
+        Error::ShellFailure { reason, .. } => Some(reason),
+        _ => None,
+    }
+}
+
+#[test]
+fn missing_command_is_a_shell_failure() {
+    let error = run_shell("sh -c", "definitely-not-a-command");
+    assert_eq!(
+        shell_failure_reason(&error),
+        Some("a command in the script could not be found")
+    );
+}
+
+#[test]
+fn shell_syntax_error_is_a_shell_failure() {
+    let error = run_shell("sh -c", "if then fi");
+    assert_eq!(
+        shell_failure_reason(&error),
+        Some("the script has a syntax error")
+    );
+}
+
+#[test]
+fn explicit_exit_127_is_a_task_failure() {
+    let error = run_shell("sh -c", "exit 127");
+    assert!(matches!(error, Error::TaskFailure { .. }), "{:?}", error);
+}
+
+#[test]
+fn syntax_errors_from_other_tools_are_task_failures() {
+    let error = run_shell(
+        "sh -c",
+        "echo 'jq: error: syntax error, unexpected INVALID_CHARACTER' >&2; exit 3",
+    );
+    assert!(matches!(error, Error::TaskFailure { .. }), "{:?}", error);
+}
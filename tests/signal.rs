@@ -0,0 +1,97 @@
+#![cfg(unix)]
+
+use shell_candy::{Error, RetryPolicy, ShellTask, ShellTaskBehavior};
+
+fn shell(script: &str) -> ShellTask {
+    ShellTask::shell(script).unwrap()
+}
+
+fn run(task: &ShellTask) -> Error {
+    task.run(|_| ShellTaskBehavior::<()>::Passthrough)
+        .unwrap_err()
+}
+
+fn run_once_with_retry(task: &ShellTask) -> Error {
+    task.retry(RetryPolicy {
+        attempts: 1,
+        ..Default::default()
+    })
+    .run(|_| ShellTaskBehavior::<()>::Passthrough)
+    .unwrap_err()
+}
+
+#[test]
+fn termination_by_signal_is_reported() {
+    let error = run(&shell("echo before; ulimit -c 0; kill -SEGV $$"));
+    assert_eq!(error.signal(), Some(11));
+    assert_eq!(error.exit_code(), None);
+    assert_eq!(
+        error.to_string(),
+        "'echo before; ulimit -c 0; kill -SEGV $$' was terminated by signal 11 (SIGSEGV)."
+    );
+    match error {
+        Error::TaskKilledBySignal {
+            signal,
+            core_dumped,
+            stdout_lines,
+            ..
+        } => {
+            assert_eq!(signal, 11);
+            assert!(!core_dumped);
+            assert_eq!(stdout_lines, vec!["before".to_string()]);
+        }
+        error => panic!("expected Error::TaskKilledBySignal, got {:?}", error),
+    }
+}
+
+#[test]
+fn core_dumps_are_mentioned() {
+    let error = Error::TaskKilledBySignal {
+        task: "crash".to_string(),
+        signal: 6,
+        core_dumped: true,
+        stdout_lines: Vec::new(),
+        stderr_lines: Vec::new(),
+    };
+    assert_eq!(
+        error.to_string(),
+        "'crash' was terminated by signal 6 (SIGABRT) (core dumped)."
+    );
+}
+
+#[test]
+fn exit_codes_are_not_signals() {
+    let error = run(&shell("exit 3"));
+    assert_eq!(error.exit_code(), Some(3));
+    assert_eq!(error.signal(), None);
+}
+
+#[test]
+fn pipeline_failures_report_the_failed_stage_signal_and_exit_code() {
+    let error = shell("kill -TERM $$")
+        .pipe(shell("cat"))
+        .run(|_| ShellTaskBehavior::<()>::Passthrough)
+        .unwrap_err();
+    assert!(matches!(error, Error::PipelineFailure { stage: 0, .. }));
+    assert_eq!(error.signal(), Some(15));
+    assert_eq!(error.exit_code(), None);
+
+    let error = shell("true")
+        .pipe(shell("exit 4"))
+        .run(|_| ShellTaskBehavior::<()>::Passthrough)
+        .unwrap_err();
+    assert_eq!(error.exit_code(), Some(4));
+    assert_eq!(error.signal(), None);
+}
+
+#[test]
+fn retry_failures_report_the_last_attempt_signal_and_exit_code() {
+    let error = run_once_with_retry(&shell("kill -TERM $$"));
+    assert!(matches!(error, Error::RetryFailure { .. }));
+    assert_eq!(error.signal(), Some(15));
+    assert_eq!(error.exit_code(), None);
+
+    let error = run_once_with_retry(&shell("exit 5"));
+    assert_eq!(error.exit_code(), Some(5));
+    assert_eq!(error.signal(), None);
+}
@@ -0,0 +1,57 @@
+use shell_candy::{
+    Error, ShellTask, ShellTaskBehavior, ShellTaskGraph, ShellTaskGraphStatus, ShellTaskGroup,
+    ShellTaskOutput,
+};
+
+fn panicking_task() -> ShellTask {
+    let mut task = ShellTask::new("true").unwrap();
+    task.success_when(|_, _, _| panic!("bad predicate"));
+    task
+}
+
+fn assert_task_panicked(result: &Result<ShellTaskOutput<()>, Error>) {
+    match result {
+        Err(Error::TaskPanicked { task, message }) => {
+            assert_eq!(task, "true");
+            assert_eq!(message, "bad predicate");
+        }
+        result => panic!("expected Error::TaskPanicked, got {:?}", result),
+    }
+}
+
+#[test]
+fn group_reports_a_panicking_task_and_keeps_the_others() {
+    let output = ShellTaskGroup::new([panicking_task(), ShellTask::new("echo hi").unwrap()])
+        .max_concurrency(1)
+        .run(|_| ShellTaskBehavior::<()>::Passthrough);
+    assert_eq!(output.results.len(), 2);
+    assert_task_panicked(&output.results[0].result);
+    assert!(matches!(
+        output.results[1].result,
+        Ok(ShellTaskOutput::CompleteOutput { .. })
+    ));
+}
+
+#[test]
+fn graph_reports_a_panicking_task_and_skips_its_dependents() {
+    let mut graph = ShellTaskGraph::new();
+    let panicking = graph.add(panicking_task()).id();
+    let dependent = graph
+        .add(ShellTask::new("echo dependent").unwrap())
+        .after(panicking)
+        .id();
+    let independent = graph.add(ShellTask::new("echo independent").unwrap()).id();
+    let report = graph.run(|_| ShellTaskBehavior::<()>::Passthrough).unwrap();
+    match &report[panicking].status {
+        ShellTaskGraphStatus::Finished(result) => assert_task_panicked(result),
+        status => panic!("expected ShellTaskGraphStatus::Finished, got {:?}", status),
+    }
+    assert!(matches!(
+        report[dependent].status,
+        ShellTaskGraphStatus::Skipped { dependency } if dependency == panicking
+    ));
+    assert!(matches!(
+        report[independent].status,
+        ShellTaskGraphStatus::Finished(Ok(_))
+    ));
+}